@@ -0,0 +1,42 @@
+//! Throughput of typing and submitting lines
+//!
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use input_mgr::RingLine;
+
+const TEXT: &[u8] = b"the quick brown fox jumps over the lazy dog";
+
+/// Types and submits one line of [TEXT]
+fn append_submit(rline: &mut RingLine<64, 80>) {
+    for c in TEXT {
+        rline.append_local_char(*c).unwrap();
+    }
+    rline.submit_local_editing();
+    black_box(&rline);
+}
+
+fn bench_append_submit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("append+submit");
+
+    // Only the local end is typing
+    let mut rline = RingLine::<64, 80>::new();
+    group.bench_function("local only", |b| b.iter(|| append_submit(&mut rline)));
+
+    // The remote end has a partial line in progress the whole time, so every
+    // submit has to move the local lines past it
+    let mut rline = RingLine::<64, 80>::new();
+    for c in TEXT.iter().take(20) {
+        rline.append_remote_char(*c).unwrap();
+    }
+    group.bench_function("remote line pending", |b| {
+        b.iter(|| append_submit(&mut rline))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_append_submit);
+criterion_main!(benches);
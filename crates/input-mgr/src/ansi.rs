@@ -0,0 +1,125 @@
+//! # ANSI Escapes
+//!
+//! An [AnsiFilter] drops ANSI escape sequences, such as `ESC [ 1 ; 31 m` color
+//! codes, from a stream of remote bytes, leaving only the visible text. It keeps
+//! its state between calls, so sequences may be split across several reads, as
+//! is common with a serial port.
+
+/// Where the filter is within an escape sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Plain text
+    Ground,
+    /// Just after `ESC`
+    Escape,
+    /// Within an `ESC` sequence, after one or more intermediate bytes
+    EscIntermediate,
+    /// Within a Control Sequence, `ESC [ ...`, e.g. SGR color codes
+    Csi,
+    /// Within an Operating System Command, `ESC ] ...`, e.g. setting the title
+    Osc,
+}
+
+/// A byte-at-a-time filter that drops ANSI escape sequences
+///
+/// Control Sequences (`ESC [`, including SGR color codes) end at their final byte,
+/// Operating System Commands (`ESC ]`) end at `BEL` or `ESC \`, and any other `ESC`
+/// sequence ends at its first non-intermediate byte. The contents of all escape
+/// sequences are dropped, everything else is passed through unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsiFilter {
+    state: State,
+}
+
+const ESC: u8 = 0x1B;
+const BEL: u8 = 0x07;
+
+impl AnsiFilter {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+        }
+    }
+
+    /// Is the filter part of the way through an escape sequence?
+    pub fn in_sequence(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    /// Feed one byte through the filter
+    ///
+    /// Returns the byte if it is not part of an escape sequence, or `None` if it
+    /// was dropped.
+    pub fn filter(&mut self, byte: u8) -> Option<u8> {
+        let (next, out) = match (self.state, byte) {
+            // An ESC anywhere starts a new sequence, which for an OSC also
+            // makes `ESC \` end it
+            (_, ESC) => (State::Escape, None),
+            (State::Ground, b) => (State::Ground, Some(b)),
+
+            (State::Escape, b'[') => (State::Csi, None),
+            (State::Escape, b']') => (State::Osc, None),
+            (State::Escape | State::EscIntermediate, 0x20..=0x2F) => (State::EscIntermediate, None),
+            (State::Escape | State::EscIntermediate, _) => (State::Ground, None),
+
+            // Final bytes end the sequence, parameters and intermediates continue it
+            (State::Csi, 0x40..=0x7E) => (State::Ground, None),
+            (State::Csi, _) => (State::Csi, None),
+
+            (State::Osc, BEL) => (State::Ground, None),
+            (State::Osc, _) => (State::Osc, None),
+        };
+        self.state = next;
+        out
+    }
+}
+
+impl Default for AnsiFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod ansi_tests {
+    use super::AnsiFilter;
+
+    fn strip(filter: &mut AnsiFilter, input: &[u8]) -> Vec<u8> {
+        input.iter().filter_map(|b| filter.filter(*b)).collect()
+    }
+
+    #[test]
+    fn sgr() {
+        let mut filter = AnsiFilter::new();
+        assert_eq!(
+            strip(&mut filter, b"\x1b[1;31merror\x1b[0m: oops"),
+            b"error: oops"
+        );
+        assert!(!filter.in_sequence());
+    }
+
+    #[test]
+    fn split_sequence() {
+        let mut filter = AnsiFilter::new();
+        assert_eq!(strip(&mut filter, b"ab\x1b[3"), b"ab");
+        assert!(filter.in_sequence());
+        assert_eq!(strip(&mut filter, b"2mcd"), b"cd");
+        assert!(!filter.in_sequence());
+    }
+
+    #[test]
+    fn other_sequences() {
+        let mut filter = AnsiFilter::new();
+        // Cursor movement, erase line, charset selection, and a window title
+        assert_eq!(
+            strip(
+                &mut filter,
+                b"a\x1b[2Kb\x1b[10;20Hc\x1b(Bd\x1b]0;title\x07e"
+            ),
+            b"abcde"
+        );
+        // OSC terminated by ST, and a two byte escape
+        assert_eq!(strip(&mut filter, b"\x1b]2;x\x1b\\f\x1b7g"), b"fg");
+        assert!(!filter.in_sequence());
+    }
+}
@@ -16,37 +16,106 @@
 //!
 //! [Bricks] is also used to provide an iterator over lines.
 
-use core::{marker::PhantomData, ptr::NonNull};
+use core::{marker::PhantomData, ops::Range, ptr::NonNull};
 
 use crate::{rot_left, rot_right};
 
+/// Why a line could not be inserted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BricksError {
+    /// Every line that could be taken is already used by an editing region
+    RegionFull,
+    /// The only lines that could be taken are `pinned` history lines
+    AllPinned,
+}
+
+/// The storage type of a single line index
+///
+/// Line indexes, and the region markers, are stored much more compactly than a
+/// `usize`, which on most hosts would otherwise take as much space as a short
+/// line. This limits the number of lines to [MAX_LINES], which is checked at
+/// compile time.
+///
+/// The width is fixed rather than chosen from `L`, as picking a type from the
+/// value of a const generic needs `generic_const_exprs`, which is not stable.
+pub(crate) type Idx = u16;
+
+/// The largest number of lines that can be indexed by [Idx]
+pub(crate) const MAX_LINES: usize = Idx::MAX as usize;
+
+/// The ordering of lines, over any storage `B` of line indexes
+///
+/// The number of lines is the length of `B`. `L` only tags the iterators this
+/// hands out, so that they match those of [RingLine](crate::RingLine).
 #[derive(Debug, PartialEq)]
-pub(crate) struct Bricks<const L: usize> {
-    idx_buf: [usize; L],
-    local_editable_end: usize,  //  0..le
-    remote_editable_end: usize, // le..re
-    history_end: usize,         // re..hi
-                                // hi..   => free
+pub(crate) struct RawBricks<B, const L: usize> {
+    idx_buf: B,
+    local_editable_end: Idx,  //  0..le
+    remote_editable_end: Idx, // le..re
+    history_end: Idx,         // re..hi
+                              // hi..   => free
+}
+
+/// The ordering of a fixed number of lines, as used by [RingLine](crate::RingLine)
+pub(crate) type Bricks<const L: usize> = RawBricks<[Idx; L], L>;
+
+/// The ordering of a number of lines chosen at runtime
+#[cfg(feature = "alloc")]
+pub(crate) type VecBricks = RawBricks<alloc::vec::Vec<Idx>, 0>;
+
+/// The region a line slot currently belongs to
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Region {
+    Local,
+    Remote,
+    History,
+    Free,
 }
 
 pub struct LineIter<'a, const L: usize, I> {
-    bricks: &'a [usize],
+    bricks: &'a [Idx],
     collection: &'a [I],
 }
 
 pub struct LineIterMut<'a, 'b, const L: usize, I> {
-    bricks: &'a [usize],
+    bricks: &'a [Idx],
     col_ptr: NonNull<[I]>,
     _cpd: PhantomData<&'b mut [I]>,
 }
 
+impl<'a, 'b, const L: usize, I> LineIterMut<'a, 'b, L, I> {
+    /// Create an iterator yielding `&mut t[idx]` for each index in `bricks`
+    ///
+    /// Soundness relies on every index being in bounds of `t`, and appearing at
+    /// most once, so no two yielded references alias. [Bricks] maintains this by
+    /// only ever permuting its indexes, which is checked here in debug builds.
+    fn new(bricks: &'a [Idx], t: &'b mut [I]) -> Self {
+        debug_assert!(
+            bricks
+                .iter()
+                .enumerate()
+                .all(|(i, idx)| usize::from(*idx) < t.len() && !bricks[..i].contains(idx)),
+            "line indexes must be unique and in bounds"
+        );
+        LineIterMut {
+            bricks,
+            col_ptr: NonNull::from(t),
+            _cpd: PhantomData,
+        }
+    }
+}
+
 impl<'a, const L: usize, I> Iterator for LineIter<'a, L, I> {
     type Item = &'a I;
 
     fn next(&mut self) -> Option<Self::Item> {
         let (now, remain) = self.bricks.split_first()?;
         self.bricks = remain;
-        self.collection.get(*now)
+        self.collection.get(usize::from(*now))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.bricks.len(), Some(self.bricks.len()))
     }
 }
 
@@ -54,7 +123,7 @@ impl<'a, const L: usize, I> DoubleEndedIterator for LineIter<'a, L, I> {
     fn next_back(&mut self) -> Option<Self::Item> {
         let (now, remain) = self.bricks.split_last()?;
         self.bricks = remain;
-        self.collection.get(*now)
+        self.collection.get(usize::from(*now))
     }
 }
 
@@ -64,7 +133,11 @@ impl<'a, 'b, const L: usize, I> Iterator for LineIterMut<'a, 'b, L, I> {
     fn next(&mut self) -> Option<Self::Item> {
         let (now, remain) = self.bricks.split_first()?;
         self.bricks = remain;
-        unsafe { Some(&mut *self.col_ptr.as_ptr().cast::<I>().add(*now)) }
+        unsafe { Some(&mut *self.col_ptr.as_ptr().cast::<I>().add(usize::from(*now))) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.bricks.len(), Some(self.bricks.len()))
     }
 }
 
@@ -72,35 +145,84 @@ impl<'a, 'b, const L: usize, I> DoubleEndedIterator for LineIterMut<'a, 'b, L, I
     fn next_back(&mut self) -> Option<Self::Item> {
         let (now, remain) = self.bricks.split_last()?;
         self.bricks = remain;
-        unsafe { Some(&mut *self.col_ptr.as_ptr().cast::<I>().add(*now)) }
+        unsafe { Some(&mut *self.col_ptr.as_ptr().cast::<I>().add(usize::from(*now))) }
     }
 }
 
+impl<'a, const L: usize, I> ExactSizeIterator for LineIter<'a, L, I> {}
+
+impl<'a, 'b, const L: usize, I> ExactSizeIterator for LineIterMut<'a, 'b, L, I> {}
+
 // lower: newest
 // higher: oldest
 
 impl<const L: usize> Bricks<L> {
-    pub fn new() -> Self {
+    const FITS_IDX: () = assert!(L <= MAX_LINES, "Too many lines!");
+
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_IDX;
+
         let mut idx_buf = [0; L];
-        idx_buf.iter_mut().enumerate().for_each(|(i, v)| *v = i);
+        let mut i = 0;
+        while i < L {
+            idx_buf[i] = i as Idx;
+            i += 1;
+        }
         Self {
-            idx_buf: idx_buf,
+            idx_buf,
             local_editable_end: 0,
             remote_editable_end: 0,
             history_end: 0,
         }
     }
+}
+
+#[cfg(feature = "alloc")]
+impl VecBricks {
+    /// Panics if `len` is larger than [MAX_LINES]
+    pub fn with_len(len: usize) -> Self {
+        assert!(len <= MAX_LINES, "Too many lines!");
+        Self {
+            idx_buf: (0..len).map(|i| i as Idx).collect(),
+            local_editable_end: 0,
+            remote_editable_end: 0,
+            history_end: 0,
+        }
+    }
+}
+
+impl<B: AsRef<[Idx]> + AsMut<[Idx]>, const L: usize> RawBricks<B, L> {
+    /// The total number of lines
+    fn len(&self) -> usize {
+        self.idx_buf.as_ref().len()
+    }
+
+    /// The end of the local editable region
+    fn le(&self) -> usize {
+        self.local_editable_end.into()
+    }
+
+    /// The end of the remote editable region
+    fn re(&self) -> usize {
+        self.remote_editable_end.into()
+    }
+
+    /// The end of the history region
+    fn hi(&self) -> usize {
+        self.history_end.into()
+    }
 
     pub fn iter_local_editable<'a, I>(&'a self, t: &'a [I]) -> LineIter<'a, L, I> {
         LineIter {
-            bricks: &self.idx_buf[0..self.local_editable_end],
+            bricks: &self.idx_buf.as_ref()[0..self.le()],
             collection: t,
         }
     }
 
     pub fn iter_remote_editable<'a, I>(&'a self, t: &'a [I]) -> LineIter<'a, L, I> {
         LineIter {
-            bricks: &self.idx_buf[self.local_editable_end..self.remote_editable_end],
+            bricks: &self.idx_buf.as_ref()[self.le()..self.re()],
             collection: t,
         }
     }
@@ -109,32 +231,62 @@ impl<const L: usize> Bricks<L> {
         &'a self,
         t: &'b mut [I],
     ) -> LineIterMut<'a, 'b, L, I> {
-        LineIterMut {
-            bricks: &self.idx_buf[0..self.local_editable_end],
-            col_ptr: NonNull::from(t),
-            _cpd: PhantomData,
-        }
+        LineIterMut::new(&self.idx_buf.as_ref()[0..self.le()], t)
     }
 
     pub fn iter_remote_editable_mut<'a, 'b, I>(
         &'a self,
         t: &'b mut [I],
     ) -> LineIterMut<'a, 'b, L, I> {
-        LineIterMut {
-            bricks: &self.idx_buf[self.local_editable_end..self.remote_editable_end],
-            col_ptr: NonNull::from(t),
-            _cpd: PhantomData,
-        }
+        LineIterMut::new(&self.idx_buf.as_ref()[self.le()..self.re()], t)
     }
 
     /// Iterate through the historical items, from NEWEST to OLDEST
     pub fn iter_history<'a, I>(&'a self, t: &'a [I]) -> LineIter<'a, L, I> {
         LineIter {
-            bricks: &self.idx_buf[self.remote_editable_end..self.history_end],
+            bricks: &self.idx_buf.as_ref()[self.re()..self.hi()],
+            collection: t,
+        }
+    }
+
+    /// Iterate through up to `count` historical items, from NEWEST to OLDEST,
+    /// starting `start` items from the NEWEST
+    pub fn iter_history_range<'a, I>(
+        &'a self,
+        t: &'a [I],
+        start: usize,
+        count: usize,
+    ) -> LineIter<'a, L, I> {
+        let start = self.re().saturating_add(start).min(self.hi());
+        let end = start.saturating_add(count).min(self.hi());
+        LineIter {
+            bricks: &self.idx_buf.as_ref()[start..end],
             collection: t,
         }
     }
 
+    /// Iterate mutably through the historical items, from NEWEST to OLDEST
+    pub fn iter_history_mut<'a, 'b, I>(&'a self, t: &'b mut [I]) -> LineIterMut<'a, 'b, L, I> {
+        LineIterMut::new(&self.idx_buf.as_ref()[self.re()..self.hi()], t)
+    }
+
+    /// Iterate through every slot in display order, yielding the index of the line
+    /// in that slot, and the region it belongs to
+    pub fn iter_slots(&self) -> impl Iterator<Item = (usize, Region)> + '_ {
+        self.idx_buf.as_ref().iter().enumerate().map(|(pos, idx)| {
+            let region = if pos < self.le() {
+                Region::Local
+            } else if pos < self.re() {
+                Region::Remote
+            } else if pos < self.hi() {
+                Region::History
+            } else {
+                Region::Free
+            };
+            (usize::from(*idx), region)
+        })
+    }
+
     pub fn pop_local_editable_front(&mut self) {
         //        0 LE1 => LE2
         //        1 LE2 => RE1            < LEND
@@ -144,10 +296,11 @@ impl<const L: usize> Bricks<L> {
         //        5 HI2 => HI3
         //        6 HI3 => LE1 (now XX1)  < HEND
         // > HEND 7 XX1 => XX1 (now XX2)
-        if self.local_editable_end == 0 {
+        if self.le() == 0 {
             return;
         }
-        rot_left(&mut self.idx_buf[..self.history_end]);
+        let hi = self.hi();
+        rot_left(&mut self.idx_buf.as_mut()[..hi]);
         self.local_editable_end -= 1;
         self.remote_editable_end -= 1;
         self.history_end -= 1;
@@ -163,72 +316,248 @@ impl<const L: usize> Bricks<L> {
         //        6 HI3 => RE1 (now XX1)  < HEND
         // > HEND 7 XX1 => XX1 (now XX2)
 
-        if self.remote_editable_end == self.local_editable_end {
+        if self.re() == self.le() {
             return;
         }
-        rot_left(&mut self.idx_buf[self.local_editable_end..self.history_end]);
+        let (le, hi) = (self.le(), self.hi());
+        rot_left(&mut self.idx_buf.as_mut()[le..hi]);
         self.remote_editable_end -= 1;
         self.history_end -= 1;
     }
 
     pub fn local_editable_front(&self) -> Option<usize> {
-        if self.local_editable_end == 0 {
+        if self.le() == 0 {
             None
         } else {
-            Some(self.idx_buf[0])
+            Some(self.idx_buf.as_ref()[0].into())
         }
     }
 
     pub fn remote_editable_front(&self) -> Option<usize> {
-        if self.remote_editable_end == self.local_editable_end {
+        if self.re() == self.le() {
             None
         } else {
-            Some(self.idx_buf[self.local_editable_end])
+            Some(self.idx_buf.as_ref()[self.le()].into())
         }
     }
 
+    /// The number of slots used by any region
+    pub fn used_len(&self) -> usize {
+        self.hi()
+    }
+
+    /// The number of slots not used by any region
+    pub fn free_len(&self) -> usize {
+        self.len() - self.hi()
+    }
+
     // Operations:
     //
     // * Insert user editable -> Fails if all items already UE
     // * Insert inco editable -> Fails if all items already UE + IE
     // * Insert history       -> Fails if all items already UE + IE (not + history!)
-    pub fn insert_local_editable_front(&mut self) -> Result<usize, ()> {
-        if self.local_editable_end == L {
-            return Err(());
+    /// Takes a free line, or the OLDEST history line, or failing that the OLDEST
+    /// remote editing line, as the new NEWEST local editing line
+    ///
+    /// History lines whose index is `pinned` are never taken.
+    pub fn insert_local_editable_front(
+        &mut self,
+        pinned: impl Fn(usize) -> bool,
+    ) -> Result<usize, BricksError> {
+        let pos = self.victim(&pinned, true)?;
+        rot_right(&mut self.idx_buf.as_mut()[..=pos]);
+        if pos >= self.re() {
+            self.remote_editable_end += 1;
         }
-        // Rotate in at least one free/history
-        let end = self.history_end.wrapping_add(1).min(L);
-        rot_right(&mut self.idx_buf[..end]);
-        self.local_editable_end = self.local_editable_end.wrapping_add(1).min(L);
-        self.remote_editable_end = self.remote_editable_end.wrapping_add(1).min(L);
-        self.history_end = self.history_end.wrapping_add(1).min(L);
-        Ok(self.idx_buf[0])
+        if pos >= self.hi() {
+            self.history_end += 1;
+        }
+        self.local_editable_end += 1;
+        Ok(self.idx_buf.as_ref()[0].into())
+    }
+
+    /// Would [RawBricks::insert_local_editable_front()] succeed?
+    #[cfg(feature = "undo")]
+    pub fn can_insert_local_editable(&self, pinned: impl Fn(usize) -> bool) -> bool {
+        self.victim(&pinned, true).is_ok()
     }
 
-    pub fn insert_remote_editable_front(&mut self) -> Result<usize, ()> {
-        if self.remote_editable_end == L {
-            return Err(());
+    /// Takes a free line, or failing that the OLDEST history line, as the new
+    /// NEWEST remote editing line
+    ///
+    /// Local editing lines are never taken: only the slots from
+    /// `local_editable_end` onwards are rotated. Once every line is a local or
+    /// remote editing line, this fails. History lines whose index is `pinned` are
+    /// never taken either.
+    pub fn insert_remote_editable_front(
+        &mut self,
+        pinned: impl Fn(usize) -> bool,
+    ) -> Result<usize, BricksError> {
+        let pos = self.victim(&pinned, false)?;
+        let le = self.le();
+        rot_right(&mut self.idx_buf.as_mut()[le..=pos]);
+        if pos >= self.hi() {
+            self.history_end += 1;
+        }
+        self.remote_editable_end += 1;
+        Ok(self.idx_buf.as_ref()[self.le()].into())
+    }
+
+    /// Takes a free line, or failing that the OLDEST history line that is not
+    /// `pinned`, as the new NEWEST history line
+    pub fn insert_history_front(
+        &mut self,
+        pinned: impl Fn(usize) -> bool,
+    ) -> Result<usize, BricksError> {
+        let pos = self.victim(&pinned, false)?;
+        let re = self.re();
+        rot_right(&mut self.idx_buf.as_mut()[re..=pos]);
+        if pos >= self.hi() {
+            self.history_end += 1;
+        }
+        Ok(self.idx_buf.as_ref()[self.re()].into())
+    }
+
+    /// The index of the history line that the next insert would recycle, if there
+    /// are no free lines
+    ///
+    /// This is the OLDEST history line whose index is not `pinned`. Taking a
+    /// remote editing line instead, as a local insert may, is not included.
+    pub fn history_victim(&self, pinned: impl Fn(usize) -> bool) -> Option<usize> {
+        let pos = self.victim(&pinned, false).ok()?;
+        (pos < self.hi()).then(|| self.idx_buf.as_ref()[pos].into())
+    }
+
+    /// The slot positions of `region`, in display order
+    pub fn range(&self, region: Region) -> Range<usize> {
+        match region {
+            Region::Local => 0..self.le(),
+            Region::Remote => self.le()..self.re(),
+            Region::History => self.re()..self.hi(),
+            Region::Free => self.hi()..self.len(),
+        }
+    }
+
+    /// The index of the line in the slot at `pos`
+    pub fn idx_at(&self, pos: usize) -> usize {
+        self.idx_buf.as_ref()[pos].into()
+    }
+
+    /// Takes a free line, or failing that the OLDEST history line that is not
+    /// `pinned`, as a new line just NEWER than the one at `pos`, in the same region
+    ///
+    /// Returns the position of the new line. The line at `pos` must be `pinned`,
+    /// so that it is not taken itself. If only NEWER history lines can be taken,
+    /// the new line is taken from those instead.
+    pub fn insert_newer_than(
+        &mut self,
+        pos: usize,
+        pinned: impl Fn(usize) -> bool,
+    ) -> Result<usize, BricksError> {
+        let victim = self.victim(&pinned, false)?;
+        if victim < pos {
+            rot_left(&mut self.idx_buf.as_mut()[victim..pos]);
+            return Ok(pos - 1);
+        }
+        let (le, re, hi) = (self.le(), self.re(), self.hi());
+        rot_right(&mut self.idx_buf.as_mut()[pos..=victim]);
+        if pos < le {
+            self.local_editable_end += 1;
+        }
+        if pos < re {
+            self.remote_editable_end += 1;
+        }
+        if victim >= hi {
+            self.history_end += 1;
+        }
+        Ok(pos)
+    }
+
+    /// Returns the line at `pos`, in any region, to the free lines
+    pub fn remove_at(&mut self, pos: usize) {
+        let (le, re, hi) = (self.le(), self.re(), self.hi());
+        rot_left(&mut self.idx_buf.as_mut()[pos..hi]);
+        if pos < le {
+            self.local_editable_end -= 1;
+        }
+        if pos < re {
+            self.remote_editable_end -= 1;
+        }
+        self.history_end -= 1;
+    }
+
+    /// The slot that a newly inserted line is taken from: the first free slot, or
+    /// the OLDEST history line that is not `pinned`, or with `steal_remote`, the
+    /// OLDEST remote editing line
+    fn victim(
+        &self,
+        pinned: &impl Fn(usize) -> bool,
+        steal_remote: bool,
+    ) -> Result<usize, BricksError> {
+        if self.hi() < self.len() {
+            return Ok(self.hi());
+        }
+        let idx_buf = self.idx_buf.as_ref();
+        let has_history = self.hi() > self.re();
+        let has_remote = self.re() > self.le();
+        (self.re()..self.hi())
+            .rev()
+            .find(|pos| !pinned(idx_buf[*pos].into()))
+            .or_else(|| (steal_remote && has_remote).then(|| self.re() - 1))
+            .ok_or(if has_history {
+                BricksError::AllPinned
+            } else {
+                BricksError::RegionFull
+            })
+    }
+
+    /// Drop the OLDEST history items until at most `max` remain, keeping any line
+    /// whose index is `pinned` even if that leaves more than `max` items
+    ///
+    /// The oldest history items are already adjacent to the free region, so
+    /// without pins this only needs to move the end marker.
+    pub fn trim_history(&mut self, max: usize, pinned: impl Fn(usize) -> bool) {
+        let hi = self.hi();
+        let mut keep = self.re().saturating_add(max).min(hi);
+        let idx_buf = self.idx_buf.as_mut();
+        let start = keep;
+        for pos in start..hi {
+            if pinned(idx_buf[pos].into()) {
+                // Move the pinned line up to just after the lines being kept
+                rot_right(&mut idx_buf[keep..=pos]);
+                keep += 1;
+            }
+        }
+        self.history_end = keep as Idx;
+    }
+
+    /// Physically reorder `t` to match the display order, resetting the line
+    /// indexes to the identity permutation
+    ///
+    /// This follows each cycle of the permutation, so needs no extra storage.
+    pub fn compact<I>(&mut self, t: &mut [I]) {
+        for start in 0..self.len() {
+            let mut cur = start;
+            loop {
+                let src = usize::from(self.idx_buf.as_ref()[cur]);
+                self.idx_buf.as_mut()[cur] = cur as Idx;
+                if src == start {
+                    break;
+                }
+                t.swap(cur, src);
+                cur = src;
+            }
         }
-        // Rotate in at least one free/history
-        let end = self.history_end.wrapping_add(1).min(L);
-        rot_right(&mut self.idx_buf[self.local_editable_end..end]);
-        self.remote_editable_end = self.remote_editable_end.wrapping_add(1).min(L);
-        self.history_end = self.history_end.wrapping_add(1).min(L);
-        Ok(self.idx_buf[self.local_editable_end])
     }
 
     pub fn submit_local_editable(&mut self) {
-        // We want to swap ue and ie regions.
-        let range = &mut self.idx_buf[..self.remote_editable_end];
-
-        // TODO(AJM): This is memory-friendly (requires only 1xusize extra),
-        // but VERY CPU-unfriendly O(n^2) copies. This can be mitigated by
-        // keeping the number of inco_editable items low, ideally 0/1.
-        //
-        // Alternatively, I could use O(n) extra storage, and assemble
-        // the output directly.
-        for _ in 0..(self.remote_editable_end - self.local_editable_end) {
-            rot_right(range);
+        // We want to swap ue and ie regions. This takes time proportional to the
+        // size of both editing regions, and nothing at all when the remote
+        // editing region is empty, which is the common case when typing.
+        let remote = self.re() - self.le();
+        if remote != 0 {
+            let re = self.re();
+            self.idx_buf.as_mut()[..re].rotate_right(remote);
         }
         self.remote_editable_end -= self.local_editable_end;
         self.local_editable_end = 0;
@@ -237,23 +566,342 @@ impl<const L: usize> Bricks<L> {
     pub fn submit_remote_editable(&mut self) {
         self.remote_editable_end = self.local_editable_end;
     }
+
+    /// Panics if the region markers are out of order, or if the line indexes are
+    /// not a permutation of `0..len`
+    #[cfg(test)]
+    pub fn check_invariants(&self) {
+        let len = self.len();
+        assert!(self.le() <= self.re());
+        assert!(self.re() <= self.hi());
+        assert!(self.hi() <= len);
+
+        let mut seen = vec![false; len];
+        for idx in self.idx_buf.as_ref().iter() {
+            let idx = usize::from(*idx);
+            assert!(
+                idx < len && !seen[idx],
+                "line indexes are not a permutation"
+            );
+            seen[idx] = true;
+        }
+    }
+}
+
+impl<const L: usize> Default for Bricks<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use core::{fmt, marker::PhantomData};
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        ser::SerializeTuple,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::{Bricks, Idx};
+
+    // Serialized as a flat tuple of:
+    //
+    // * local_editable_end
+    // * remote_editable_end
+    // * history_end
+    // * idx_buf[0..L]
+    impl<const L: usize> Serialize for Bricks<L> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tup = serializer.serialize_tuple(3 + L)?;
+            tup.serialize_element(&usize::from(self.local_editable_end))?;
+            tup.serialize_element(&usize::from(self.remote_editable_end))?;
+            tup.serialize_element(&usize::from(self.history_end))?;
+            for idx in self.idx_buf.iter() {
+                tup.serialize_element(&usize::from(*idx))?;
+            }
+            tup.end()
+        }
+    }
+
+    struct BricksVisitor<const L: usize>(PhantomData<Bricks<L>>);
+
+    impl<'de, const L: usize> Visitor<'de> for BricksVisitor<L> {
+        type Value = Bricks<L>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "region markers followed by a permutation of 0..{}", L)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut next = |i: usize| -> Result<usize, A::Error> {
+                seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))
+            };
+
+            // The line iterators index into the line storage using these values
+            // (unchecked in the case of LineIterMut), so a corrupt blob must be
+            // rejected here rather than trusted.
+            let (le, re, hi) = (next(0)?, next(1)?, next(2)?);
+            if !(le <= re && re <= hi && hi <= L) {
+                return Err(de::Error::custom("region markers out of order"));
+            }
+
+            // In range of `Idx`, as `L` is
+            let mut brick = Bricks::<L> {
+                idx_buf: [0; L],
+                local_editable_end: le as Idx,
+                remote_editable_end: re as Idx,
+                history_end: hi as Idx,
+            };
+            let mut seen = [false; L];
+            for (i, idx) in brick.idx_buf.iter_mut().enumerate() {
+                let val = next(3 + i)?;
+                match seen.get_mut(val) {
+                    Some(s) if !*s => *s = true,
+                    _ => return Err(de::Error::custom("line indexes are not a permutation")),
+                }
+                *idx = val as Idx;
+            }
+
+            Ok(brick)
+        }
+    }
+
+    impl<'de, const L: usize> Deserialize<'de> for Bricks<L> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_tuple(3 + L, BricksVisitor(PhantomData))
+        }
+    }
 }
 
 #[cfg(test)]
 pub mod brick_tests {
-    use super::Bricks;
+    use proptest::prelude::*;
+
+    use super::{Bricks, BricksError, Region};
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        InsertLocal,
+        InsertRemote,
+        InsertHistory,
+        PopLocal,
+        PopRemote,
+        SubmitLocal,
+        SubmitRemote,
+        TrimHistory(usize),
+        Compact,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            Just(Op::InsertLocal),
+            Just(Op::InsertRemote),
+            Just(Op::InsertHistory),
+            Just(Op::PopLocal),
+            Just(Op::PopRemote),
+            Just(Op::SubmitLocal),
+            Just(Op::SubmitRemote),
+            (0..8usize).prop_map(Op::TrimHistory),
+            Just(Op::Compact),
+        ]
+    }
+
+    /// The expected contents of each region, NEWEST first
+    #[derive(Default)]
+    struct Model {
+        local: Vec<u32>,
+        remote: Vec<u32>,
+        history: Vec<u32>,
+    }
+
+    impl Model {
+        fn editing(&self) -> usize {
+            self.local.len() + self.remote.len()
+        }
+
+        /// Recycles the OLDEST history line if every line is in use. Local
+        /// editing may also take the OLDEST remote editing line.
+        fn make_room(&mut self, len: usize) {
+            if self.editing() + self.history.len() == len && self.history.pop().is_none() {
+                self.remote.pop();
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn random_ops(ops in prop::collection::vec(op(), 0..64)) {
+            const L: usize = 6;
+            let mut brick = Bricks::<L>::new();
+            let mut items = [0u32; L];
+            let mut model = Model::default();
+            let mut tag = 0;
+
+            for op in ops {
+                tag += 1;
+                match op {
+                    Op::InsertLocal | Op::InsertRemote | Op::InsertHistory => {
+                        let res = match op {
+                            Op::InsertLocal => brick.insert_local_editable_front(|_| false),
+                            Op::InsertRemote => brick.insert_remote_editable_front(|_| false),
+                            _ => brick.insert_history_front(|_| false),
+                        };
+                        let full = match op {
+                            Op::InsertLocal => model.local.len() == L,
+                            _ => model.editing() == L,
+                        };
+                        if full {
+                            prop_assert!(res.is_err());
+                            continue;
+                        }
+                        items[res.unwrap()] = tag;
+                        model.make_room(L);
+                        let region = match op {
+                            Op::InsertLocal => &mut model.local,
+                            Op::InsertRemote => &mut model.remote,
+                            _ => &mut model.history,
+                        };
+                        region.insert(0, tag);
+                    }
+                    Op::PopLocal => {
+                        brick.pop_local_editable_front();
+                        if !model.local.is_empty() {
+                            model.local.remove(0);
+                        }
+                    }
+                    Op::PopRemote => {
+                        brick.pop_remote_editable_front();
+                        if !model.remote.is_empty() {
+                            model.remote.remove(0);
+                        }
+                    }
+                    Op::SubmitLocal => {
+                        brick.submit_local_editable();
+                        model.history.splice(0..0, model.local.drain(..));
+                    }
+                    Op::SubmitRemote => {
+                        brick.submit_remote_editable();
+                        model.history.splice(0..0, model.remote.drain(..));
+                    }
+                    Op::TrimHistory(max) => {
+                        brick.trim_history(max, |_| false);
+                        model.history.truncate(max);
+                    }
+                    Op::Compact => brick.compact(&mut items),
+                }
+
+                brick.check_invariants();
+                let local = brick.iter_local_editable(&items).copied().collect::<Vec<_>>();
+                let remote = brick.iter_remote_editable(&items).copied().collect::<Vec<_>>();
+                let history = brick.iter_history(&items).copied().collect::<Vec<_>>();
+                prop_assert_eq!(&local, &model.local);
+                prop_assert_eq!(&remote, &model.remote);
+                prop_assert_eq!(&history, &model.history);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "line indexes must be unique")]
+    fn duplicate_mut_indexes() {
+        let brick = Bricks::<4> {
+            idx_buf: [1, 1, 2, 3],
+            local_editable_end: 2,
+            remote_editable_end: 2,
+            history_end: 2,
+        };
+        let mut items = [10, 11, 12, 13];
+        let _ = brick.iter_local_editable_mut(&mut items);
+    }
+
+    #[test]
+    fn compact() {
+        let mut brick = Bricks::<8> {
+            idx_buf: [4, 3, 2, 7, 6, 5, 1, 0],
+            local_editable_end: 2,
+            remote_editable_end: 4,
+            history_end: 7,
+        };
+        let mut items = [10, 11, 12, 13, 14, 15, 16, 17];
+        let before = brick.iter_history(&items).copied().collect::<Vec<_>>();
+
+        brick.compact(&mut items);
+        assert_eq!(brick.idx_buf, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(items, [14, 13, 12, 17, 16, 15, 11, 10]);
+        assert!(brick.iter_history(&items).copied().eq(before));
+        assert_eq!(brick.local_editable_end, 2);
+        assert_eq!(brick.remote_editable_end, 4);
+        assert_eq!(brick.history_end, 7);
+    }
+
+    #[test]
+    fn compact_indexes() {
+        use core::mem::size_of;
+
+        // Each line index and region marker is a u16 rather than a usize
+        assert_eq!(size_of::<Bricks<16>>(), (16 + 3) * size_of::<u16>());
+        assert!(size_of::<Bricks<16>>() < (16 + 3) * size_of::<usize>());
+    }
+
+    #[test]
+    fn iter_both_ends() {
+        let mut brick = Bricks::<8>::new();
+        for _ in 0..5 {
+            brick.insert_local_editable_front(|_| false).unwrap();
+        }
+        let items = [10, 11, 12, 13, 14, 15, 16, 17];
+
+        // Forward is newest to oldest
+        let fwd = brick.iter_local_editable(&items);
+        assert_eq!(fwd.len(), 5);
+        assert!(fwd.copied().eq([14, 13, 12, 11, 10]));
+
+        // Reverse is oldest to newest
+        let rev = brick.iter_local_editable(&items).rev();
+        assert_eq!(rev.len(), 5);
+        assert!(rev.copied().eq([10, 11, 12, 13, 14]));
+
+        // Meeting in the middle yields every item exactly once
+        let mut iter = brick.iter_local_editable(&items);
+        assert_eq!(iter.next(), Some(&14));
+        assert_eq!(iter.next_back(), Some(&10));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some(&11));
+        assert_eq!(iter.next(), Some(&13));
+        assert_eq!(iter.next_back(), Some(&12));
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        // Same for the mutable variant
+        let mut items = items;
+        let mut iter = brick.iter_local_editable_mut(&mut items);
+        assert_eq!(iter.len(), 5);
+        *iter.next().unwrap() += 100;
+        *iter.next_back().unwrap() += 200;
+        assert_eq!(iter.len(), 3);
+        iter.rev().for_each(|i| *i += 300);
+        assert_eq!(items, [210, 311, 312, 313, 114, 15, 16, 17]);
+    }
 
     #[test]
     fn smoke() {
         let mut brick = Bricks::<8>::new();
         println!("{:?}", brick);
         for i in 0..8 {
-            let x = brick.insert_local_editable_front().unwrap();
+            let x = brick.insert_local_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
         println!("{:?}", brick);
-        brick.insert_local_editable_front().unwrap_err();
+        assert_eq!(
+            brick.insert_local_editable_front(|_| false),
+            Err(BricksError::RegionFull)
+        );
         assert_eq!(
             brick,
             Bricks {
@@ -266,7 +914,7 @@ pub mod brick_tests {
         println!("=====");
         let mut brick = Bricks::<8>::new();
         for i in 0..4 {
-            let x = brick.insert_local_editable_front().unwrap();
+            let x = brick.insert_local_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -281,7 +929,7 @@ pub mod brick_tests {
         );
         println!("-----");
         for i in 4..8 {
-            let x = brick.insert_remote_editable_front().unwrap();
+            let x = brick.insert_remote_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -298,12 +946,12 @@ pub mod brick_tests {
         println!("=====");
         let mut brick = Bricks::<8>::new();
         for i in 0..3 {
-            let x = brick.insert_local_editable_front().unwrap();
+            let x = brick.insert_local_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
         for i in 3..5 {
-            let x = brick.insert_remote_editable_front().unwrap();
+            let x = brick.insert_remote_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -343,7 +991,7 @@ pub mod brick_tests {
         );
         println!("=====");
         for i in 5..8 {
-            let x = brick.insert_local_editable_front().unwrap();
+            let x = brick.insert_local_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -359,7 +1007,7 @@ pub mod brick_tests {
         );
         println!("-----");
         for i in 0..2 {
-            let x = brick.insert_remote_editable_front().unwrap();
+            let x = brick.insert_remote_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -418,7 +1066,7 @@ pub mod brick_tests {
 
         println!("-----");
         for i in 2..5 {
-            let x = brick.insert_local_editable_front().unwrap();
+            let x = brick.insert_local_editable_front(|_| false).unwrap();
             println!("{:?}", brick);
             assert_eq!(x, i);
         }
@@ -432,7 +1080,113 @@ pub mod brick_tests {
                 history_end: 8,
             }
         );
-        brick.insert_remote_editable_front().unwrap_err();
-        assert_eq!(brick.insert_local_editable_front().unwrap(), 0);
+        brick.insert_remote_editable_front(|_| false).unwrap_err();
+        assert_eq!(brick.insert_local_editable_front(|_| false).unwrap(), 0);
+    }
+
+    #[test]
+    fn history_front() {
+        let mut brick = Bricks::<4>::new();
+        assert_eq!(brick.insert_local_editable_front(|_| false).unwrap(), 0);
+        assert_eq!(brick.insert_history_front(|_| false).unwrap(), 1);
+        assert_eq!(brick.insert_history_front(|_| false).unwrap(), 2);
+        assert_eq!(
+            brick,
+            Bricks {
+                idx_buf: [0, 2, 1, 3],
+                local_editable_end: 1,
+                remote_editable_end: 1,
+                history_end: 3,
+            }
+        );
+
+        // Recycles the oldest history once free lines are gone
+        assert_eq!(brick.insert_history_front(|_| false).unwrap(), 3);
+        assert_eq!(brick.insert_history_front(|_| false).unwrap(), 1);
+        assert_eq!(
+            brick,
+            Bricks {
+                idx_buf: [0, 1, 3, 2],
+                local_editable_end: 1,
+                remote_editable_end: 1,
+                history_end: 4,
+            }
+        );
+
+        // But never editing lines
+        for _ in 0..3 {
+            brick.insert_remote_editable_front(|_| false).unwrap();
+        }
+        brick.insert_history_front(|_| false).unwrap_err();
+    }
+
+    #[test]
+    fn pinned() {
+        let mut brick = Bricks::<4>::new();
+        for _ in 0..4 {
+            brick.insert_history_front(|_| false).unwrap();
+        }
+        assert_eq!(brick.idx_buf, [3, 2, 1, 0]);
+
+        // Line 0 is the oldest, but pinned, so line 1 is taken instead
+        let pinned = |idx| idx == 0;
+        assert_eq!(brick.insert_local_editable_front(pinned).unwrap(), 1);
+        assert_eq!(brick.insert_remote_editable_front(pinned).unwrap(), 2);
+        assert_eq!(brick.insert_history_front(pinned).unwrap(), 3);
+        assert_eq!(brick.idx_buf, [1, 2, 3, 0]);
+
+        // Once all history is pinned, only local editing can take a line, from
+        // the remote end
+        let pinned = |idx| idx == 0 || idx == 3;
+        let err = Err(BricksError::AllPinned);
+        assert_eq!(brick.insert_history_front(pinned), err);
+        assert_eq!(brick.insert_remote_editable_front(pinned), err);
+        assert_eq!(brick.insert_local_editable_front(pinned).unwrap(), 2);
+        assert_eq!(brick.insert_local_editable_front(pinned), err);
+        brick.check_invariants();
+
+        // Trimming keeps pinned lines, in order
+        let mut brick = Bricks::<4>::new();
+        for _ in 0..4 {
+            brick.insert_history_front(|_| false).unwrap();
+        }
+        brick.trim_history(1, |idx| idx == 0 || idx == 2);
+        assert_eq!(
+            brick.iter_history(&[0, 1, 2, 3]).collect::<Vec<_>>(),
+            [&3, &2, &0]
+        );
+        brick.check_invariants();
+    }
+
+    #[test]
+    fn insert_newer_than() {
+        let mut brick = Bricks::<5>::new();
+        brick.insert_history_front(|_| false).unwrap();
+        brick.insert_history_front(|_| false).unwrap();
+        brick.insert_local_editable_front(|_| false).unwrap();
+        assert_eq!(brick.idx_buf, [2, 1, 0, 3, 4]);
+
+        // Takes a free line first, in any region
+        assert_eq!(brick.insert_newer_than(2, |idx| idx == 0).unwrap(), 2);
+        assert_eq!(brick.insert_newer_than(0, |idx| idx == 2).unwrap(), 0);
+        assert_eq!(brick.idx_buf, [4, 2, 1, 3, 0]);
+        assert_eq!(brick.range(Region::Local), 0..2);
+        assert_eq!(brick.range(Region::History), 2..5);
+        brick.check_invariants();
+
+        // Then the oldest history, or failing that, NEWER history
+        assert_eq!(brick.insert_newer_than(3, |idx| idx == 3).unwrap(), 3);
+        assert_eq!(brick.idx_buf, [4, 2, 1, 0, 3]);
+        let pinned = |idx| idx == 3 || idx == 0;
+        assert_eq!(brick.insert_newer_than(4, pinned).unwrap(), 3);
+        assert_eq!(brick.idx_buf, [4, 2, 0, 1, 3]);
+        brick.check_invariants();
+
+        brick.remove_at(0);
+        brick.remove_at(1);
+        assert_eq!(brick.idx_buf, [2, 1, 3, 0, 4]);
+        assert_eq!(brick.range(Region::Local), 0..1);
+        assert_eq!(brick.range(Region::Free), 3..5);
+        brick.check_invariants();
     }
 }
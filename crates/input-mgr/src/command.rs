@@ -0,0 +1,88 @@
+//! # Commands
+//!
+//! A [CommandBuf] receives a submitted local line from
+//! [RingLine::submit_local_editing_command()](crate::RingLine::submit_local_editing_command),
+//! already split into a command and its arguments, for the common REPL pattern
+//! of dispatching on the first word.
+
+use crate::LineError;
+
+/// Storage for the text of a submitted command, split at its first space
+///
+/// Holds up to `N` bytes. This does not allocate, so `N` should be large enough
+/// for the longest command that is expected, up to `L * C` for a whole
+/// [RingLine](crate::RingLine).
+#[derive(Debug, Clone)]
+pub struct CommandBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    split: Option<usize>,
+}
+
+impl<const N: usize> CommandBuf<N> {
+    /// Create a new, empty command buffer
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            split: None,
+        }
+    }
+
+    /// The whole text of the command, including any arguments
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    /// Is the command empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The text before the first space, or the whole text if there is none
+    pub fn cmd(&self) -> &str {
+        self.split().0
+    }
+
+    /// The text after the first space, or `""` if there is none
+    pub fn args(&self) -> &str {
+        self.split().1
+    }
+
+    /// Both the [CommandBuf::cmd()] and its [CommandBuf::args()]
+    pub fn split(&self) -> (&str, &str) {
+        let text = self.as_str();
+        match self.split {
+            Some(pos) => (&text[..pos], &text[pos + 1..]),
+            None => (text, ""),
+        }
+    }
+
+    /// Replace the contents with the concatenation of `parts`
+    ///
+    /// If it does not fit, [LineError::Full] is returned and the buffer is left
+    /// empty.
+    pub(crate) fn fill<'a>(
+        &mut self,
+        parts: impl Iterator<Item = &'a str>,
+    ) -> Result<(), LineError> {
+        self.len = 0;
+        self.split = None;
+        for part in parts {
+            let Some(dest) = self.buf.get_mut(self.len..self.len + part.len()) else {
+                self.len = 0;
+                return Err(LineError::Full);
+            };
+            dest.copy_from_slice(part.as_bytes());
+            self.len += part.len();
+        }
+        self.split = self.buf[..self.len].iter().position(|c| *c == b' ');
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for CommandBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -12,7 +12,9 @@
 //!
 //! It uses a fixed-size array of lines for storage, where each line can hold
 //! a dynamic number of characters. These lines can be cheaply reordered (using
-//! a separate index for the ordering of lines).
+//! a separate index for the ordering of lines). With the `alloc` feature,
+//! `RingLineVec` provides the same behavior with the number and length of lines
+//! chosen at runtime instead.
 //!
 //! These lines are sorted into four distinct regions:
 //!
@@ -35,12 +37,33 @@
 
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod ansi;
 mod bricks;
+mod command;
 mod lines;
+#[cfg(feature = "embedded-io")]
+mod sink;
+mod snapshot;
+mod source;
+#[cfg(feature = "alloc")]
+mod vec;
+
+use core::{iter::Take, ops::Range};
 
-use bricks::Bricks;
-pub use bricks::{LineIter, LineIterMut};
+pub use ansi::AnsiFilter;
+use bricks::{Bricks, BricksError};
+pub use bricks::{LineIter, LineIterMut, Region};
+pub use command::CommandBuf;
 pub use lines::Line;
+#[cfg(feature = "embedded-io")]
+pub use sink::RemoteSink;
+pub use snapshot::{DiffResult, SnapshotBuf};
+pub use source::{DisplayLine, RingSource};
+#[cfg(feature = "alloc")]
+pub use vec::{LineVec, RingLineVec};
 
 /// # RingLine
 ///
@@ -53,28 +76,59 @@ pub use lines::Line;
 ///
 /// In general, `L` should be >= the number of lines you intend to display. If L is
 /// larger than the number of lines you would like to display, it can also be used
-/// as a "scrollback" buffer.
+/// as a "scrollback" buffer. `L` may not be larger than `u16::MAX`.
 ///
 /// RingLine does NOT store lines in a "sparse" manner - if you have 16 lines and 80
 /// characters per line, 1280 bytes will be used to store those characters, even if
-/// all lines are blank.
+/// all lines are blank. With the `undo` feature, a second copy of up to `L` lines
+/// is kept for `RingLine::undo_local()`, so the total is about twice that.
 #[derive(Debug)]
 pub struct RingLine<const L: usize, const C: usize> {
     lines: [Line<C>; L],
     brick: Bricks<L>,
+    next_seq: u64,
+    soft_limit: usize,
+    /// Set by [RingLine::remote_carriage_return()]: the position in the NEWEST
+    /// remote editing line that the next remote character overwrites
+    remote_overwrite: Option<usize>,
+    /// Set by [RingLine::on_evict()]
+    on_evict: Option<fn(&Line<C>)>,
+    wrap: WrapStrategy,
+    /// The local editing region before the last edit, see [RingLine::undo_local()]
+    #[cfg(feature = "undo")]
+    undo: SnapshotBuf<L, C>,
+    #[cfg(feature = "undo")]
+    undo_valid: bool,
 }
 
 impl<const L: usize, const C: usize> RingLine<L, C> {
     const ONELINE: Line<C> = Line::<C>::new();
     const INIT: [Line<C>; L] = [Self::ONELINE; L];
 
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             lines: Self::INIT,
             brick: Bricks::new(),
+            next_seq: 0,
+            soft_limit: C,
+            remote_overwrite: None,
+            on_evict: None,
+            wrap: WrapStrategy::Wrap,
+            #[cfg(feature = "undo")]
+            undo: SnapshotBuf::new(),
+            #[cfg(feature = "undo")]
+            undo_valid: false,
         }
     }
 
+    /// The number of bytes a [RingLine] of this size takes, including padding
+    ///
+    /// This is a `const fn`, so it can be checked in a `const` assertion, to be sure
+    /// at compile time that it fits in the RAM set aside for it.
+    pub const fn byte_size() -> usize {
+        core::mem::size_of::<Self>()
+    }
+
     /// Iterates all "historical" (e.g. not currently editing) lines, NEWEST to OLDEST
     ///
     /// Each line contans a status field that marks it as local or remote.
@@ -82,16 +136,227 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
     /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
     /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
     pub fn iter_history(&self) -> LineIter<'_, L, Line<C>> {
-        let Self { lines, brick } = self;
+        let Self { lines, brick, .. } = self;
         brick.iter_history(lines)
     }
 
+    /// Iterates up to `count` "historical" lines, NEWEST to OLDEST, starting at
+    /// `start` as used by [RingLine::history()]
+    ///
+    /// This is the same as `iter_history().skip(start).take(count)`, but takes
+    /// constant time to set up, e.g. to draw one page of a long scrollback. If
+    /// `start` is past the OLDEST line, the iterator is empty.
+    pub fn iter_history_range(&self, start: usize, count: usize) -> LineIter<'_, L, Line<C>> {
+        let Self { lines, brick, .. } = self;
+        brick.iter_history_range(lines, start, count)
+    }
+
+    /// Iterates the "historical" lines from `src` only, NEWEST to OLDEST
+    ///
+    /// Editing lines always come from the side that is editing them, so there is
+    /// no need to filter those.
+    pub fn iter_history_source(&self, src: Source) -> impl DoubleEndedIterator<Item = &Line<C>> {
+        self.iter_history().filter(move |l| l.status() == src)
+    }
+
+    /// Iterates exactly `rows` rows of history for a fixed-height grid, top to
+    /// bottom: the NEWEST `rows` history lines, OLDEST first, with `None` for
+    /// each blank row above them if there are not enough lines to fill the grid
+    pub fn iter_history_padded(&self, rows: usize) -> impl Iterator<Item = Option<&Line<C>>> + '_ {
+        let shown = self.iter_history().len().min(rows);
+        core::iter::repeat_n(None, rows - shown)
+            .chain(self.iter_history().take(shown).rev().map(Some))
+    }
+
+    /// The NEWEST line of the local editing region, which is where typed
+    /// characters go. Returns `None` if the region is empty.
+    pub fn current_local_line(&self) -> Option<&Line<C>> {
+        self.brick
+            .local_editable_front()
+            .map(|idx| &self.lines[idx])
+    }
+
+    /// The NEWEST line of the remote editing region, which is where received
+    /// characters go. Returns `None` if the region is empty.
+    pub fn current_remote_line(&self) -> Option<&Line<C>> {
+        self.brick
+            .remote_editable_front()
+            .map(|idx| &self.lines[idx])
+    }
+
+    /// Obtain a single "historical" line by index, where `0` is the NEWEST line
+    ///
+    /// Returns `None` if there are not that many lines of history.
+    pub fn history(&self, idx: usize) -> Option<&Line<C>> {
+        self.iter_history().nth(idx)
+    }
+
+    /// Writes the whole message that the history line at `idx` is part of to `out`
+    ///
+    /// Long messages wrap across several lines of history. This joins all of the
+    /// lines of the message containing `idx` (see [RingLine::history()]), OLDEST to
+    /// NEWEST, back into the text as it was originally written. If the start of
+    /// the message has already been recycled, only the remaining part is written.
+    /// Nothing is written if there are not that many lines of history.
+    pub fn history_message(&self, idx: usize, out: &mut dyn core::fmt::Write) -> core::fmt::Result {
+        let len = self.iter_history().len();
+        if idx >= len {
+            return Ok(());
+        }
+
+        // Older lines are part of the message as long as the newer line
+        // after them is a continuation.
+        let older = self
+            .iter_history()
+            .skip(idx)
+            .take_while(|l| l.is_continuation())
+            .count();
+        let start = (idx + older).min(len - 1);
+        let newer = self
+            .iter_history()
+            .take(idx)
+            .rev()
+            .take_while(|l| l.is_continuation())
+            .count();
+        let end = idx - newer;
+
+        for line in self.iter_history().skip(end).take(start - end + 1).rev() {
+            out.write_str(line.as_str())?;
+        }
+        Ok(())
+    }
+
+    /// Streams the whole history, OLDEST to NEWEST, to `out` without allocating
+    ///
+    /// `out` is called with the bytes of each line, with a `b"\n"` between
+    /// messages. Lines that continue a wrapped message are joined to it without a
+    /// separator. See [RingLine::export_bytes_prefixed()] to also tag each message
+    /// with its source.
+    pub fn export_bytes(&self, out: &mut dyn FnMut(&[u8])) {
+        self.export(false, out)
+    }
+
+    /// Like [RingLine::export_bytes()], but each message starts with `b"L: "`,
+    /// `b"R: "`, or `b"S: "`, for local, remote, and system messages respectively
+    pub fn export_bytes_prefixed(&self, out: &mut dyn FnMut(&[u8])) {
+        self.export(true, out)
+    }
+
+    fn export(&self, prefixed: bool, out: &mut dyn FnMut(&[u8])) {
+        for (i, line) in self.iter_history().rev().enumerate() {
+            if !line.is_continuation() || i == 0 {
+                if i != 0 {
+                    out(b"\n");
+                }
+                if prefixed {
+                    out(match line.status() {
+                        Source::Local => b"L: ",
+                        Source::Remote => b"R: ",
+                        Source::System => b"S: ",
+                    });
+                }
+            }
+            out(line.as_bytes());
+        }
+    }
+
+    /// Search the history, NEWEST to OLDEST, for a line containing `needle`
+    ///
+    /// The search starts at history index `from` (inclusive, with `0` being the NEWEST
+    /// line), and returns the index of the first matching line, suitable for use with
+    /// [RingLine::history()]. To find the next older match, search again from the
+    /// returned index plus one.
+    ///
+    /// Matching is case sensitive, see [RingLine::find_history_ci()] for a case
+    /// insensitive search.
+    pub fn find_history(&self, needle: &str, from: usize) -> Option<usize> {
+        self.iter_history()
+            .enumerate()
+            .skip(from)
+            .find(|(_, line)| line.contains(needle.as_bytes()))
+            .map(|(i, _)| i)
+    }
+
+    /// Search the history, NEWEST to OLDEST, for a line containing `needle`, ignoring
+    /// ascii case
+    ///
+    /// See [RingLine::find_history()] for details.
+    pub fn find_history_ci(&self, needle: &str, from: usize) -> Option<usize> {
+        let needle = needle.as_bytes();
+        self.iter_history()
+            .enumerate()
+            .skip(from)
+            .find(|(_, line)| {
+                needle.is_empty()
+                    || line
+                        .as_str()
+                        .as_bytes()
+                        .windows(needle.len())
+                        .any(|w| w.eq_ignore_ascii_case(needle))
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Iterates all lines in display order, from the BOTTOM of the display to the TOP
+    ///
+    /// This yields the local editing region, then the remote editing region, then the
+    /// history region, each NEWEST to OLDEST, tagged with the region they came from.
+    ///
+    /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
+    /// [Iterator::rev()] to obtain lines in TOP to BOTTOM order.
+    pub fn iter_all(&self) -> AllLinesIter<'_, L, C> {
+        AllLinesIter {
+            local: self.iter_local_editing(),
+            remote: self.iter_remote_editing(),
+            history: self.iter_history(),
+        }
+    }
+
+    /// Iterates every physical line slot, including free ones, for diagnostics
+    ///
+    /// Yields the index of the slot in the underlying storage, the [Region] it
+    /// currently belongs to, and the line stored in it. Slots are yielded in display
+    /// order: local editing, remote editing, history (each NEWEST to OLDEST), then
+    /// free slots.
+    ///
+    /// Free slots may still contain stale contents from before they were freed.
+    pub fn debug_slots(&self) -> impl Iterator<Item = (usize, Region, &Line<C>)> + '_ {
+        self.brick
+            .iter_slots()
+            .map(|(idx, region)| (idx, region, &self.lines[idx]))
+    }
+
+    /// Copy the current ordered lines of all regions into the provided [SnapshotBuf]
+    ///
+    /// The snapshot does not borrow the [RingLine], allowing it to be rendered while
+    /// the [RingLine] continues to be modified. Any previous contents of the snapshot
+    /// are replaced.
+    pub fn snapshot(&self, buf: &mut SnapshotBuf<L, C>) {
+        buf.load(
+            self.iter_local_editing(),
+            self.iter_remote_editing(),
+            self.iter_history(),
+        );
+    }
+
+    /// Compare the current lines of all regions against an earlier [SnapshotBuf]
+    ///
+    /// This can be used to only redraw the lines that have changed since the
+    /// snapshot was taken, see [DiffResult].
+    pub fn diff(&self, prev: &SnapshotBuf<L, C>) -> DiffResult<L> {
+        prev.diff(
+            self.iter_local_editing(),
+            self.iter_remote_editing(),
+            self.iter_history(),
+        )
+    }
+
     /// Iterates any lines that are currently being edited by the remote end, NEWEST to OLDEST
     ///
     /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
     /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
     pub fn iter_remote_editing(&self) -> LineIter<'_, L, Line<C>> {
-        let Self { lines, brick } = self;
+        let Self { lines, brick, .. } = self;
         brick.iter_remote_editable(lines)
     }
 
@@ -105,6 +370,23 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
         self.iter_remote_editing().map(|l| l.len()).sum()
     }
 
+    /// The number of ascii characters/bytes currently used by all history lines
+    pub fn history_len(&self) -> usize {
+        self.iter_history().map(|l| l.len()).sum()
+    }
+
+    /// Iterates the contents of the local editing region as one continuous stream
+    /// of bytes, across any wrapped lines
+    ///
+    /// Bytes are yielded OLDEST to NEWEST, in the order they were typed. The returned
+    /// iterator implements [DoubleEndedIterator], and can be reversed with
+    /// [Iterator::rev()] to obtain the NEWEST byte first.
+    pub fn local_editing_chars(&self) -> impl DoubleEndedIterator<Item = u8> + '_ {
+        self.iter_local_editing()
+            .rev()
+            .flat_map(|l| l.as_bytes().iter().copied())
+    }
+
     /// Attempt to copy the entire current local editing buffer to a provided slice
     ///
     /// Useful for obtaining the full user input prior to submitting the line.
@@ -132,7 +414,7 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
 
             let (now, later) = window.split_at_mut(needed);
             window = later;
-            now.copy_from_slice(l.as_str().as_bytes());
+            now.copy_from_slice(l.as_bytes());
         }
 
         let taken = buf_len - window.len();
@@ -144,7 +426,7 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
     /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
     /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
     pub fn iter_local_editing(&self) -> LineIter<'_, L, Line<C>> {
-        let Self { lines, brick } = self;
+        let Self { lines, brick, .. } = self;
         brick.iter_local_editable(lines)
     }
 
@@ -153,7 +435,7 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
     /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
     /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
     pub fn iter_remote_editing_mut(&mut self) -> LineIterMut<'_, '_, L, Line<C>> {
-        let Self { lines, brick } = self;
+        let Self { lines, brick, .. } = self;
         brick.iter_remote_editable_mut(lines)
     }
 
@@ -162,124 +444,1367 @@ impl<const L: usize, const C: usize> RingLine<L, C> {
     /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
     /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
     pub fn iter_local_editing_mut(&mut self) -> LineIterMut<'_, '_, L, Line<C>> {
-        let Self { lines, brick } = self;
+        let Self { lines, brick, .. } = self;
         brick.iter_local_editable_mut(lines)
     }
 
     /// Moves the local editing region into a user historical region
+    ///
+    /// The submitted lines will have no [Line::timestamp()], see
+    /// [RingLine::submit_local_editing_at()].
+    ///
+    /// This takes time proportional to the number of editing lines, and is
+    /// fastest while the remote editing region is empty. When typing quickly,
+    /// submitting often keeps the local editing region, and so each submit, small.
     pub fn submit_local_editing(&mut self) {
-        self.brick.submit_local_editable();
+        self.submit_local(None);
+    }
+
+    /// Moves the local editing region into a user historical region, tagging each
+    /// submitted line with the given timestamp
+    ///
+    /// This crate doesn't interpret `ticks` in any way, the caller may use whatever
+    /// clock and units are convenient. It is available afterwards from
+    /// [Line::timestamp()].
+    pub fn submit_local_editing_at(&mut self, ticks: u64) {
+        self.submit_local(Some(ticks));
     }
 
     /// Moves the remote editing region into a user historical region
+    ///
+    /// The submitted lines will have no [Line::timestamp()], see
+    /// [RingLine::submit_remote_editing_at()].
     pub fn submit_remote_editing(&mut self) {
+        self.submit_remote(None);
+    }
+
+    /// Moves the remote editing region into a user historical region, tagging each
+    /// submitted line with the given timestamp
+    ///
+    /// See [RingLine::submit_local_editing_at()].
+    pub fn submit_remote_editing_at(&mut self, ticks: u64) {
+        self.submit_remote(Some(ticks));
+    }
+
+    /// Moves the local editing region into a user historical region, after removing
+    /// any trailing spaces
+    ///
+    /// Lines left empty by trimming are dropped, so input that wrapped only because
+    /// of trailing spaces is submitted as fewer lines.
+    pub fn submit_local_editing_trimmed(&mut self) {
+        let Self { lines, brick, .. } = self;
+        while let Some(wr) = brick.local_editable_front() {
+            let cur = &mut lines[wr];
+            cur.trim_end();
+            if !cur.is_empty() {
+                break;
+            }
+            brick.pop_local_editable_front();
+        }
+        self.submit_local_editing();
+    }
+
+    /// Moves the local editing region into a user historical region, unless it is
+    /// blank
+    ///
+    /// The region is blank if it has no characters after the prompt, or only
+    /// spaces. A blank region is cleared rather than submitted, so pressing enter on
+    /// it doesn't clutter the history. Any prompt set with
+    /// [RingLine::set_local_prompt()] is kept. Returns whether anything was
+    /// submitted.
+    pub fn submit_local_editing_nonempty(&mut self) -> bool {
+        let blank = self
+            .iter_local_editing()
+            .all(|l| l.editable_str().bytes().all(|c| c == b' '));
+        if blank {
+            if self.replace_local_editing("").is_err() {
+                self.clear_local_editing();
+            }
+        } else {
+            self.submit_local_editing();
+        }
+        !blank
+    }
+
+    /// Writes the text of the local editing region to `out`, then moves it into a
+    /// user historical region
+    ///
+    /// Wrapped lines are joined, so `out` receives the text exactly as it was typed.
+    /// If writing fails, the error is returned and nothing is submitted.
+    pub fn submit_local_editing_into(
+        &mut self,
+        out: &mut dyn core::fmt::Write,
+    ) -> core::fmt::Result {
+        for line in self.iter_local_editing().rev() {
+            out.write_str(line.as_str())?;
+        }
+        self.submit_local_editing();
+        Ok(())
+    }
+
+    /// Copies the text of the local editing region to `out`, split into a command
+    /// and its arguments at the first space, then moves it into a user historical
+    /// region
+    ///
+    /// Wrapped lines are joined, and any prompt set with
+    /// [RingLine::set_local_prompt()] is left out of `out`, though it is still
+    /// submitted to the history. If the text does not fit in `out`,
+    /// [LineError::Full] is returned and nothing is submitted.
+    pub fn submit_local_editing_command<const N: usize>(
+        &mut self,
+        out: &mut CommandBuf<N>,
+    ) -> Result<(), LineError> {
+        out.fill(self.iter_local_editing().rev().map(Line::editable_str))?;
+        self.submit_local_editing();
+        Ok(())
+    }
+
+    /// Moves the local editing region into a user historical region, returning the
+    /// lines that were just submitted, NEWEST to OLDEST
+    ///
+    /// There may be more than one line if the input wrapped, or none if the local
+    /// editing region was empty.
+    pub fn submit_local_editing_and_get(&mut self) -> Take<LineIter<'_, L, Line<C>>> {
+        let count = self.iter_local_editing().len();
+        self.submit_local_editing();
+        self.iter_history().take(count)
+    }
+
+    /// Moves the remote editing region into a user historical region, returning the
+    /// lines that were just submitted, NEWEST to OLDEST
+    ///
+    /// See [RingLine::submit_local_editing_and_get()].
+    pub fn submit_remote_editing_and_get(&mut self) -> Take<LineIter<'_, L, Line<C>>> {
+        let count = self.iter_remote_editing().len();
+        self.submit_remote_editing();
+        self.iter_history().take(count)
+    }
+
+    fn submit_local(&mut self, timestamp: Option<u64>) {
+        let Self {
+            lines,
+            brick,
+            next_seq,
+            ..
+        } = self;
+        for line in brick.iter_local_editable_mut(lines).rev() {
+            line.set_seq(*next_seq);
+            line.set_timestamp(timestamp);
+            *next_seq += 1;
+        }
+        self.brick.submit_local_editable();
+        #[cfg(feature = "undo")]
+        {
+            self.undo_valid = false;
+        }
+    }
+
+    fn submit_remote(&mut self, timestamp: Option<u64>) {
+        self.remote_overwrite = None;
+        let Self {
+            lines,
+            brick,
+            next_seq,
+            ..
+        } = self;
+        for line in brick.iter_remote_editable_mut(lines).rev() {
+            line.set_seq(*next_seq);
+            line.set_timestamp(timestamp);
+            *next_seq += 1;
+        }
         self.brick.submit_remote_editable();
     }
 
+    /// Iterates all "historical" lines NEWEST to OLDEST, along with their sequence number
+    ///
+    /// Every line is given a unique sequence number when it enters the history region,
+    /// counting up from zero. Sequence numbers are never reused, so they remain a stable
+    /// way to refer to a line even as other lines are added and evicted. Wrapped input
+    /// takes one sequence number per line. Lines split off by [RingLine::rewrap()] are
+    /// given new numbers, so are out of order with the lines around them.
+    ///
+    /// The returned iterator implements [DoubleEndedIterator], and can be reversed with
+    /// [Iterator::rev()] to obtain lines in OLDEST to NEWEST order.
+    pub fn iter_history_seq(&self) -> impl DoubleEndedIterator<Item = (u64, &Line<C>)> + '_ {
+        self.iter_history().map(|line| (line.seq(), line))
+    }
+
+    /// Obtain the "historical" line with the given sequence number
+    ///
+    /// Returns `None` if the line has been evicted, or if no line has been given
+    /// that sequence number yet. See [RingLine::iter_history_seq()].
+    pub fn history_by_seq(&self, seq: u64) -> Option<&Line<C>> {
+        self.iter_history().find(|line| line.seq() == seq)
+    }
+
+    /// Latches a [Source::System] line directly into the history region
+    ///
+    /// This skips the editing phase entirely, and does not affect the contents of
+    /// either editing region. Strings longer than `C` are wrapped across multiple
+    /// history lines.
+    ///
+    /// Does NOT accept control characters, such as `\n`. If any character is invalid,
+    /// or the whole message does not fit in the lines not used by the editing
+    /// regions or pinned history, nothing is added to the history.
+    pub fn push_system_line(&mut self, s: &str) -> Result<(), RingLineError> {
+        if !s.bytes().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+            return Err(RingLineError::Line(LineError::InvalidChar));
+        }
+        let pinned = self.iter_history().filter(|l| l.is_pinned()).count();
+        let available =
+            L - self.iter_local_editing().count() - self.iter_remote_editing().count() - pinned;
+        if s.len().div_ceil(self.soft_limit).max(1) > available {
+            return Err(RingLineError::Line(LineError::Full));
+        }
+
+        // Always push at least one line, even for an empty string
+        let mut chunks = s.as_bytes().chunks(self.soft_limit);
+        let mut next = chunks.next().or(Some(&[]));
+        let mut wrapped = false;
+        while let Some(chunk) = next {
+            let Self {
+                lines,
+                brick,
+                on_evict,
+                ..
+            } = self;
+            Self::evicting(*on_evict, lines, brick);
+            let idx = brick.insert_history_front(|idx| lines[idx].is_pinned())?;
+            let line = &mut self.lines[idx];
+            line.clear();
+            line.set_status(Source::System);
+            line.set_continuation(wrapped);
+            line.set_seq(self.next_seq);
+            self.next_seq += 1;
+            // Already validated above, and each chunk is at most `soft_limit <= C` long
+            let _ = line.extend(core::str::from_utf8(chunk).unwrap_or(""));
+            next = chunks.next();
+            wrapped = true;
+        }
+        Ok(())
+    }
+
+    /// Pushes each of `lines` as a standalone message at the NEWEST end of the
+    /// history, e.g. to replay a saved transcript
+    ///
+    /// Each message is tagged with its [Source] and has no
+    /// [Line::timestamp()]. Messages longer than the
+    /// [RingLine::soft_line_limit()] wrap across multiple lines, and the OLDEST
+    /// history is recycled as needed. The editing regions are not affected.
+    /// Messages with characters that are not printable ASCII are handled as set
+    /// by `invalid`.
+    ///
+    /// Returns the number of messages loaded, or [RingLineError::BufferExhausted]
+    /// or [RingLineError::AllPinned] if no line can be taken for a message, in
+    /// which case the messages before it have already been loaded.
+    pub fn load_history<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = (Source, &'a str)>,
+        invalid: OnInvalid,
+    ) -> Result<usize, RingLineError> {
+        let mut loaded = 0;
+        let mut like = Line::<C>::new();
+        for (source, s) in lines {
+            let replacement = match invalid {
+                _ if s.bytes().all(|c| lines::ascii_good(c).is_ok()) => 0,
+                OnInvalid::Replace(c) if lines::ascii_good(c).is_ok() => c,
+                _ => continue,
+            };
+            like.set_status(source);
+
+            let mut idx = self.push_history_like(&like, false)?;
+            for c in s.chars() {
+                if self.lines[idx].len() >= self.soft_limit {
+                    idx = self.push_history_like(&like, true)?;
+                }
+                let c = ascii_byte(c)
+                    .ok()
+                    .filter(|c| lines::ascii_good(*c).is_ok())
+                    .unwrap_or(replacement);
+                // Already checked or replaced above
+                let _ = self.lines[idx].push(c);
+            }
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Appends a complete line to the local editing region, and then submits it
+    ///
+    /// This behaves as if the local end typed every character of `s` and then
+    /// submitted, wrapping across multiple lines if `s` is longer than `C`. Any
+    /// content already in the local editing region is submitted along with it.
+    ///
+    /// Does NOT accept control characters, such as `\n`. If any character is
+    /// invalid, or the whole line does not fit in the buffer, the local editing
+    /// region is cleared and nothing is submitted.
+    pub fn append_local_line(&mut self, s: &str) -> Result<(), RingLineError> {
+        self.fill_local_line(s)?;
+        self.submit_local_editing();
+        Ok(())
+    }
+
+    /// Like [RingLine::append_local_line()], also setting the
+    /// [attribute](Line::attr()) of every line submitted
+    #[cfg(feature = "attrs")]
+    pub fn append_local_line_with_attr(&mut self, s: &str, attr: u8) -> Result<(), RingLineError> {
+        self.fill_local_line(s)?;
+        self.iter_local_editing_mut().for_each(|l| l.set_attr(attr));
+        self.submit_local_editing();
+        Ok(())
+    }
+
+    /// Appends `s` to the local editing region for [RingLine::append_local_line()],
+    /// clearing the region instead if it does not fit
+    fn fill_local_line(&mut self, s: &str) -> Result<(), RingLineError> {
+        let res = s.bytes().try_for_each(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                Ok(())
+            } else {
+                Err(RingLineError::Line(LineError::InvalidChar))
+            }
+        });
+        // Only the newest local editing line can be partially filled, so this is
+        // exactly how many lines the submitted content will take. Check it up front,
+        // so that a line that can never fit doesn't evict history on the way.
+        let res = res.and_then(|()| {
+            let needed = (self.local_editing_len() + s.len())
+                .max(1)
+                .div_ceil(self.soft_limit);
+            if needed > self.local_lines_available() {
+                Err(RingLineError::Line(LineError::Full))
+            } else {
+                Ok(())
+            }
+        });
+        let res = res.and_then(|()| s.bytes().try_for_each(|c| self.push_local_char(c)));
+        if res.is_err() {
+            self.clear_local_editing();
+        }
+        res
+    }
+
+    /// Replaces the local editing region with a copy of a history line, so that it
+    /// can be edited and submitted again (e.g. "up arrow" recall)
+    ///
+    /// `history_idx` is as used by [RingLine::history()], where `0` is the NEWEST
+    /// line. Only [Source::Local] lines can be recalled. The history itself is not
+    /// modified, though as with any editing the OLDEST history may be recycled if
+    /// there are no free lines. A prompt set with [RingLine::set_local_prompt()] is
+    /// kept, and only the text after the recalled line's own prompt is copied.
+    ///
+    /// Returns [RingLineError::NoSuchLine] if there are not that many lines of
+    /// history, or [RingLineError::NotLocal] if the line did not come from the
+    /// local side. The local editing region is unchanged in either case, or if the
+    /// text can't be placed, see [RingLine::replace_local_editing()].
+    pub fn recall_history_into_local(&mut self, history_idx: usize) -> Result<(), RingLineError> {
+        // Copy out first: making room for the editing line may recycle the very
+        // history line we are recalling.
+        let recalled = self
+            .history(history_idx)
+            .ok_or(RingLineError::NoSuchLine)?
+            .clone();
+        if recalled.status() != Source::Local {
+            return Err(RingLineError::NotLocal);
+        }
+        self.replace_local_editing(recalled.editable_str())
+    }
+
+    /// Replaces everything in the local editing region with `s`, e.g. for tab
+    /// completion
+    ///
+    /// `s` is wrapped across multiple lines as if it was typed, see
+    /// [RingLine::soft_line_limit()] and [RingLine::set_wrap_strategy()]. With
+    /// [WrapStrategy::Reject], nothing is changed if `s` needs more than one line.
+    /// A prompt set with
+    /// [RingLine::set_local_prompt()] is kept, and `s` follows it.
+    ///
+    /// Does NOT accept control characters, such as `\n`. Nothing is changed if
+    /// any character is invalid, if `s` does not fit in the lines that are not
+    /// used by the remote editing region or pinned history, or if any local
+    /// editing line is [read-only](Line::set_read_only()).
+    pub fn replace_local_editing(&mut self, s: &str) -> Result<(), RingLineError> {
+        s.bytes().try_for_each(lines::ascii_good)?;
+        if self.iter_local_editing().any(Line::is_read_only) {
+            return Err(RingLineError::Line(LineError::ReadOnly));
+        }
+        // Copy out the prompt, the line holding it is cleared below
+        let prompt = self
+            .iter_local_editing()
+            .next_back()
+            .filter(|l| l.prefix_len() != 0)
+            .cloned();
+        let prompt_len = prompt.as_ref().map(Line::prefix_len).unwrap_or(0);
+        let needed = (prompt_len + s.len()).div_ceil(self.soft_limit);
+        let rejected = self.wrap == WrapStrategy::Reject && needed > 1;
+        if prompt_len > self.soft_limit || needed > self.local_lines_available() || rejected {
+            return Err(RingLineError::Line(LineError::Full));
+        }
+
+        #[cfg(feature = "undo")]
+        self.save_undo();
+        self.clear_local_editing();
+        if let Some(prompt) = prompt {
+            self.set_local_prompt(&prompt.as_str()[..prompt_len])?;
+        }
+        s.bytes().try_for_each(|c| self.push_local_char(c))
+    }
+
+    /// Changes the [Source] of a history line, e.g. to correct its attribution
+    ///
+    /// `idx` is as used by [RingLine::history()], where `0` is the NEWEST line.
+    /// Only this one line is changed, even if it is part of a longer message that
+    /// wrapped across several lines. The order of the history is not affected.
+    ///
+    /// Returns [RingLineError::NoSuchLine] if there are not that many lines of
+    /// history.
+    pub fn set_history_status(&mut self, idx: usize, source: Source) -> Result<(), RingLineError> {
+        let Self { lines, brick, .. } = self;
+        brick
+            .iter_history_mut(lines)
+            .nth(idx)
+            .ok_or(RingLineError::NoSuchLine)?
+            .set_status(source);
+        Ok(())
+    }
+
+    /// Pins a history line, so that it is never recycled for new lines
+    ///
+    /// `idx` is as used by [RingLine::history()], where `0` is the NEWEST line. A
+    /// pinned line stays in the history, in order, while OLDER unpinned lines are
+    /// recycled around it, until it is released with [RingLine::unpin()]. Once
+    /// every history line is pinned, new lines can only be taken from the free
+    /// lines. Pinned lines are left as they are by [RingLine::rewrap()]. Pins are
+    /// not kept when serialized.
+    ///
+    /// Returns [RingLineError::NoSuchLine] if there are not that many lines of
+    /// history.
+    pub fn pin_history(&mut self, idx: usize) -> Result<PinId, RingLineError> {
+        let Self { lines, brick, .. } = self;
+        let line = brick
+            .iter_history_mut(lines)
+            .nth(idx)
+            .ok_or(RingLineError::NoSuchLine)?;
+        line.set_pinned(true);
+        Ok(PinId(line.seq()))
+    }
+
+    /// Releases a line pinned with [RingLine::pin_history()], so that it can be
+    /// recycled as usual
+    ///
+    /// Returns [RingLineError::NoSuchLine] if the line is no longer in the history.
+    pub fn unpin(&mut self, pin: PinId) -> Result<(), RingLineError> {
+        let Self { lines, brick, .. } = self;
+        brick
+            .iter_history_mut(lines)
+            .find(|line| line.seq() == pin.seq())
+            .ok_or(RingLineError::NoSuchLine)?
+            .set_pinned(false);
+        Ok(())
+    }
+
+    /// Drops the OLDEST history lines until at most `max` remain
+    ///
+    /// The dropped lines are returned to the pool of empty lines, and will be reused
+    /// before any remaining history is recycled. The editing regions are not affected.
+    /// [Pinned](RingLine::pin_history()) lines are never dropped, so more than `max`
+    /// lines may remain. Each dropped line is passed to the
+    /// [RingLine::on_evict()] hook, OLDEST first.
+    pub fn trim_history(&mut self, max: usize) {
+        if let Some(f) = self.on_evict {
+            self.iter_history()
+                .skip(max)
+                .rev()
+                .filter(|l| !l.is_pinned())
+                .for_each(f);
+        }
+        let Self { lines, brick, .. } = self;
+        brick.trim_history(max, |idx| lines[idx].is_pinned());
+    }
+
+    /// Removes the `n` OLDEST history lines, passing each to `out` first, OLDEST
+    /// first, e.g. to move them to a larger scrollback elsewhere
+    ///
+    /// As with [RingLine::trim_history()], the lines are returned to the pool of
+    /// empty lines, the editing regions are not affected, and
+    /// [pinned](RingLine::pin_history()) lines are skipped and kept. The
+    /// [RingLine::on_evict()] hook is not called, as `out` sees every line.
+    pub fn drain_oldest_history(&mut self, n: usize, out: &mut impl FnMut(Source, &str)) {
+        let keep = self.iter_history().len().saturating_sub(n);
+        self.iter_history()
+            .skip(keep)
+            .rev()
+            .filter(|l| !l.is_pinned())
+            .for_each(|l| out(l.status(), l.as_str()));
+        let Self { lines, brick, .. } = self;
+        brick.trim_history(keep, |idx| lines[idx].is_pinned());
+    }
+
+    /// Physically reorders the line storage to match the display order
+    ///
+    /// Over time, the lines backing each region end up scattered through memory.
+    /// This does not change the contents or order of any region, but can make
+    /// later iteration more cache friendly. It takes time proportional to `L`, so
+    /// is best called when otherwise idle.
+    pub fn compact(&mut self) {
+        let Self { lines, brick, .. } = self;
+        brick.compact(lines);
+    }
+
+    /// Empties every region, e.g. for a "clear screen" command
+    ///
+    /// This leaves the ring as it was after [RingLine::new()], reusing the existing
+    /// storage in time proportional to `L`. Pins, prompts and read-only lines are
+    /// all cleared, and the [RingLine::on_evict()] hook is not called. The
+    /// [RingLine::soft_line_limit()] and the hook itself are kept, as are sequence
+    /// numbers, which continue to count up.
+    pub fn clear_all(&mut self) {
+        self.lines.iter_mut().for_each(Line::clear);
+        self.brick = Bricks::new();
+        self.remote_overwrite = None;
+        #[cfg(feature = "undo")]
+        {
+            self.undo_valid = false;
+        }
+    }
+
+    /// Are all regions empty, as they are after [RingLine::new()]?
+    pub fn is_empty(&self) -> bool {
+        self.used_lines() == 0
+    }
+
+    /// The number of lines used by history and both editing regions
+    pub fn used_lines(&self) -> usize {
+        self.brick.used_len()
+    }
+
+    /// Limits the number of characters per line to `limit`, which is clamped to
+    /// at most `C`
+    ///
+    /// Appending to the local or remote editing region wraps to a new line once
+    /// the current one reaches the limit, as do system lines. This allows one
+    /// `RingLine` to emulate terminals of different widths. Lines that are already
+    /// longer than the new limit are left as they are. The limit starts as `C`.
+    pub fn set_soft_line_limit(&mut self, limit: u16) {
+        self.soft_limit = usize::from(limit).clamp(1, C.max(1));
+    }
+
+    /// Sets what appending to a local or remote editing line does once it is
+    /// full, see [WrapStrategy]
+    ///
+    /// The strategy starts as [WrapStrategy::Wrap]. Other strategies keep each
+    /// editing region to a single line, but lines that have already wrapped are
+    /// left as they are. System lines, and [RingLine::rewrap()], always wrap.
+    pub fn set_wrap_strategy(&mut self, wrap: WrapStrategy) {
+        self.wrap = wrap;
+    }
+
+    /// The current [WrapStrategy], see [RingLine::set_wrap_strategy()]
+    pub fn wrap_strategy(&self) -> WrapStrategy {
+        self.wrap
+    }
+
+    /// Re-splits every line at a new [soft line limit](RingLine::set_soft_line_limit())
+    ///
+    /// Lines that were wrapped are joined back together and split again at
+    /// `new_limit`, so content can follow a change in display width. This is done
+    /// in place, keeping the order, source, timestamp, sequence number, prompt and
+    /// read-only flag of every line. A history line split off at the new limit is
+    /// given a new sequence number, and a line that is joined completely into the
+    /// one before it is dropped, along with its sequence number.
+    ///
+    /// [Pinned](RingLine::pin_history()) and read-only lines are left as they are,
+    /// and are not joined with the lines around them, so each [PinId] stays valid.
+    ///
+    /// If the rewrapped lines no longer fit, the OLDEST unpinned history lines are
+    /// dropped. Once there are none left, any line that could not be split is left
+    /// longer than `new_limit`, and [RingLineError::BufferExhausted] or
+    /// [RingLineError::AllPinned] is returned.
+    pub fn rewrap(&mut self, new_limit: u16) -> Result<(), RingLineError> {
+        self.set_soft_line_limit(new_limit);
+        self.remote_overwrite = None;
+        #[cfg(feature = "undo")]
+        {
+            self.undo_valid = false;
+        }
+        // History goes first, so that the editing regions can push out the oldest
+        // history if needed.
+        let history = self.rewrap_region(Region::History);
+        let remote = self.rewrap_region(Region::Remote);
+        let local = self.rewrap_region(Region::Local);
+        history.and(remote).and(local)
+    }
+
+    /// Rewraps the lines of one region in place, OLDEST to NEWEST, see
+    /// [RingLine::rewrap()]
+    fn rewrap_region(&mut self, region: Region) -> Result<(), RingLineError> {
+        let limit = self.soft_limit;
+        let Range { start, mut end } = self.brick.range(region);
+        let mut res = Ok(());
+        let fixed = |line: &Line<C>| line.is_pinned() || line.is_read_only();
+        // `end` is one past the line being rewrapped, which moves towards `start`
+        // as each NEWER line is reached
+        while end > start {
+            let Self {
+                lines,
+                brick,
+                on_evict,
+                next_seq,
+                ..
+            } = self;
+            let pos = end - 1;
+            let cur = brick.idx_at(pos);
+            end = pos;
+            if fixed(&lines[cur]) {
+                continue;
+            }
+
+            if lines[cur].len() > limit {
+                // Split the excess off into a new NEWER line, without taking this one
+                let pinned = |idx: usize| idx == cur || lines[idx].is_pinned();
+                let victim = on_evict.zip(brick.history_victim(pinned));
+                if let Some((f, idx)) = victim {
+                    f(&lines[idx]);
+                }
+                match brick.insert_newer_than(pos, pinned) {
+                    Ok(new_pos) => {
+                        let new = brick.idx_at(new_pos);
+                        if let Ok([cur, new]) = lines.get_disjoint_mut([cur, new]) {
+                            cur.wrap_into(limit, new);
+                            if region == Region::History {
+                                new.set_seq(*next_seq);
+                                *next_seq += 1;
+                            }
+                        }
+                        end = new_pos + 1;
+                    }
+                    Err(e) => res = Err(e.into()),
+                }
+            } else if lines[cur].len() < limit && pos > start {
+                // Join the start of the next line on, if it continues this one
+                let next = brick.idx_at(pos - 1);
+                if !lines[next].is_continuation() || fixed(&lines[next]) {
+                    continue;
+                }
+                if let Ok([cur, next]) = lines.get_disjoint_mut([cur, next]) {
+                    cur.pull_from(next, limit - cur.len());
+                    if next.is_empty() {
+                        // This line moves up into the removed one's place, so is
+                        // looked at again, as it may need more of the line after
+                        next.clear();
+                        brick.remove_at(pos - 1);
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    /// Pushes a new, empty, NEWEST history line with the same source and timestamp
+    /// as `like`, returning its index
+    fn push_history_like(
+        &mut self,
+        like: &Line<C>,
+        continued: bool,
+    ) -> Result<usize, RingLineError> {
+        let Self {
+            lines,
+            brick,
+            on_evict,
+            ..
+        } = self;
+        Self::evicting(*on_evict, lines, brick);
+        let idx = brick.insert_history_front(|idx| lines[idx].is_pinned())?;
+        let line = &mut self.lines[idx];
+        line.clear();
+        line.set_status(like.status());
+        line.set_timestamp(like.timestamp());
+        line.set_continuation(continued);
+        line.set_seq(self.next_seq);
+        self.next_seq += 1;
+        Ok(idx)
+    }
+
+    /// The current number of characters per line, see
+    /// [RingLine::set_soft_line_limit()]
+    pub fn soft_line_limit(&self) -> usize {
+        self.soft_limit
+    }
+
+    /// The number of characters that can still be typed before the local editing
+    /// region wraps onto a new line
+    ///
+    /// This respects the [RingLine::soft_line_limit()]. If nothing is being typed,
+    /// this is the space on the line that the next character would start.
+    pub fn local_line_remaining(&self) -> usize {
+        let used = self.current_local_line().map(Line::len).unwrap_or(0);
+        self.soft_limit.saturating_sub(used)
+    }
+
+    /// Set a protected prompt at the start of the local editing region
+    ///
+    /// The prompt is placed on the OLDEST local editing line, which is created if
+    /// nothing is being typed, and replaces any previous prompt on that line.
+    /// Backspacing with [RingLine::pop_local_char()] or
+    /// [RingLine::pop_local_word()] stops at the prompt, see [Line::prefix_len()].
+    ///
+    /// The prompt is part of the line, so it is kept in history once the local
+    /// editing region is submitted.
+    pub fn set_local_prompt(&mut self, s: &str) -> Result<(), RingLineError> {
+        let limit = self.soft_limit;
+        if self.brick.local_editable_front().is_none() {
+            self.get_local_first_writeable()?;
+        }
+        let line = self
+            .iter_local_editing_mut()
+            .next_back()
+            .ok_or(RingLineError::NoFreeLines)?;
+        line.set_prefix(s, limit)?;
+        Ok(())
+    }
+
+    /// Sets a hook that is called with each history line just before it is
+    /// recycled, e.g. to persist it to flash first
+    ///
+    /// The hook is called while the line still holds its content, whenever the
+    /// OLDEST history line is taken for a new editing or history line because
+    /// there are no free lines left, and for each line dropped by
+    /// [RingLine::trim_history()]. Pass `None` to remove the hook. The hook is not
+    /// serialized.
+    pub fn on_evict(&mut self, hook: Option<fn(&Line<C>)>) {
+        self.on_evict = hook;
+    }
+
+    /// The number of lines that are not used by history or either editing region
+    ///
+    /// New editing lines are taken from these first. Once none are left, the
+    /// OLDEST history line is recycled instead.
+    pub fn free_lines(&self) -> usize {
+        self.brick.free_len()
+    }
+
     /// Attempts to append a character to the local editing region
     ///
-    /// Does NOT accept control characters, such as `\n`.
+    /// Does NOT accept control characters, such as `\n`. If a new line is needed
+    /// but none can be taken, [RingLineError::BufferExhausted] is returned. Once
+    /// the line is full, see [RingLine::set_wrap_strategy()].
     pub fn append_local_char(&mut self, c: u8) -> Result<(), RingLineError> {
-        self.get_local_first_writeable()
-            .ok_or(RingLineError::Line(LineError::Full))?
-            .push(c)?;
+        lines::ascii_good(c)?;
+        #[cfg(feature = "undo")]
+        if self.can_push_local() {
+            self.save_undo();
+        }
+        self.push_local_char(c)
+    }
+
+    /// [RingLine::append_local_char()], without saving the region for undo
+    fn push_local_char(&mut self, c: u8) -> Result<(), RingLineError> {
+        let wrap = self.wrap;
+        Self::push_or_truncate(self.get_local_first_writeable(), c, wrap)
+    }
+
+    /// Like [RingLine::append_local_char()], also setting the
+    /// [attribute](Line::attr()) of the line the character is appended to
+    #[cfg(feature = "attrs")]
+    pub fn append_local_char_with_attr(&mut self, c: u8, attr: u8) -> Result<(), RingLineError> {
+        self.append_local_char(c)?;
+        if let Some(line) = self.iter_local_editing_mut().next() {
+            line.set_attr(attr);
+        }
         Ok(())
     }
 
+    /// Pushes `c` to the editing line from `get_*_first_writeable()`, unless it is
+    /// full and `wrap` is [WrapStrategy::Truncate]
+    fn push_or_truncate(
+        line: Result<&mut Line<C>, RingLineError>,
+        c: u8,
+        wrap: WrapStrategy,
+    ) -> Result<(), RingLineError> {
+        match line {
+            Ok(line) => Ok(line.push(c)?),
+            Err(RingLineError::Line(LineError::Full)) if wrap == WrapStrategy::Truncate => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Attempts to append a [char] to the local editing region
+    ///
+    /// Like [RingLine::append_local_char()], but returns [LineError::InvalidChar]
+    /// for any non-ascii character.
+    pub fn append_local(&mut self, c: char) -> Result<(), RingLineError> {
+        self.append_local_char(ascii_byte(c)?)
+    }
+
+    /// Attempts to append a [char] to the remote editing region
+    ///
+    /// Like [RingLine::append_remote_char()], but returns [LineError::InvalidChar]
+    /// for any non-ascii character.
+    pub fn append_remote(&mut self, c: char) -> Result<(), RingLineError> {
+        self.append_remote_char(ascii_byte(c)?)
+    }
+
     /// Attempts to append a character to the remote editing region
     ///
+    /// If a new line is needed, free lines are used first, then the OLDEST history
+    /// lines. Local editing lines are never taken, so once every line is being
+    /// edited, this returns [RingLineError::BufferExhausted] until a region is
+    /// submitted.
+    ///
+    /// After a [RingLine::remote_carriage_return()], characters overwrite the
+    /// NEWEST remote editing line instead, until they pass its end.
+    ///
     /// Does NOT accept control characters, such as `\n`.
     pub fn append_remote_char(&mut self, c: u8) -> Result<(), RingLineError> {
-        self.get_remote_first_writeable()
-            .ok_or(RingLineError::Line(LineError::Full))?
-            .push(c)?;
+        if let Some(col) = self.remote_overwrite.take() {
+            let Self { lines, brick, .. } = self;
+            if let Some(cur) = brick.iter_remote_editable_mut(lines).next() {
+                if col < cur.len() {
+                    if let Err(e) = cur.overwrite(col, c) {
+                        // a rejected character doesn't end the overwrite
+                        self.remote_overwrite = Some(col);
+                        return Err(e.into());
+                    }
+                    if col + 1 < cur.len() {
+                        self.remote_overwrite = Some(col + 1);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        let wrap = self.wrap;
+        Self::push_or_truncate(self.get_remote_first_writeable(), c, wrap)
+    }
+
+    /// Like [RingLine::append_remote_char()], also setting the
+    /// [attribute](Line::attr()) of the line the character is written to
+    #[cfg(feature = "attrs")]
+    pub fn append_remote_char_with_attr(&mut self, c: u8, attr: u8) -> Result<(), RingLineError> {
+        self.append_remote_char(c)?;
+        if let Some(line) = self.iter_remote_editing_mut().next() {
+            line.set_attr(attr);
+        }
         Ok(())
     }
 
+    /// Replaces the line being received in the remote editing region with `s`,
+    /// without submitting it, e.g. to redraw a progress bar in place
+    ///
+    /// The remote editing region only ever holds the one line being received, so
+    /// all of it is replaced, including any rows it wrapped onto. `s` is wrapped
+    /// as if it was received one character at a time, see
+    /// [RingLine::set_wrap_strategy()], and any pending
+    /// [RingLine::remote_carriage_return()] is cancelled.
+    ///
+    /// Does NOT accept control characters, such as `\r`. Nothing is changed if any
+    /// character is invalid, if `s` does not fit in the lines that are not used
+    /// by the local editing region or pinned history, or if any remote editing
+    /// line is [read-only](Line::set_read_only()).
+    pub fn set_remote_editing_line(&mut self, s: &str) -> Result<(), RingLineError> {
+        s.bytes().try_for_each(lines::ascii_good)?;
+        if self.iter_remote_editing().any(Line::is_read_only) {
+            return Err(RingLineError::Line(LineError::ReadOnly));
+        }
+        let pinned = self.iter_history().filter(|l| l.is_pinned()).count();
+        let available = L - self.iter_local_editing().count() - pinned;
+        let needed = s.len().div_ceil(self.soft_limit).max(1);
+        let rejected = self.wrap == WrapStrategy::Reject && needed > 1;
+        if needed > available || rejected {
+            return Err(RingLineError::Line(LineError::Full));
+        }
+
+        self.remote_overwrite = None;
+        while self.brick.remote_editable_front().is_some() {
+            self.brick.pop_remote_editable_front();
+        }
+        // Keep an empty line, rather than none at all
+        self.get_remote_first_writeable()?;
+        s.bytes().try_for_each(|c| self.append_remote_char(c))
+    }
+
+    /// Attempts to append a character to the remote editing region, without
+    /// recycling any history
+    ///
+    /// This behaves like [RingLine::append_remote_char()], except that if a new line
+    /// is needed and there are no [free lines](RingLine::free_lines()) left,
+    /// [RingLineError::NoFreeLines] is returned and nothing is changed. This allows
+    /// a caller to pause a stream of remote data rather than lose history.
+    pub fn try_append_remote_char(&mut self, c: u8) -> Result<(), RingLineError> {
+        let needs_line = match self.brick.remote_editable_front() {
+            Some(wr) => {
+                let cur = &self.lines[wr];
+                let overwriting = matches!(self.remote_overwrite, Some(col) if col < cur.len());
+                !overwriting && cur.len() >= self.soft_limit
+            }
+            None => true,
+        };
+        if needs_line && self.free_lines() == 0 {
+            return Err(RingLineError::NoFreeLines);
+        }
+        self.append_remote_char(c)
+    }
+
+    /// Splits the NEWEST local editing line at `at`, like pressing Enter within a
+    /// multi-line compose box, without submitting anything
+    ///
+    /// Everything from `at` onwards moves to a new NEWEST local editing line, which
+    /// is not a [continuation](Line::is_continuation()) of the one before it, so
+    /// the two are submitted as separate messages. RingLine has no cursor, so the
+    /// caller passes its position, and typing continues at the end of the new
+    /// line. Does nothing if the local editing region is empty.
+    ///
+    /// Nothing is changed if the line can't be split there, see
+    /// [Line::split_into()], or if no new line can be taken.
+    pub fn split_local_at(&mut self, at: usize) -> Result<(), RingLineError> {
+        let Self {
+            lines,
+            brick,
+            on_evict,
+            ..
+        } = self;
+        let Some(cur) = brick.local_editable_front() else {
+            return Ok(());
+        };
+        let mut tail = Line::new();
+        lines[cur].split_into(at, &mut tail)?;
+
+        Self::evicting(*on_evict, lines, brick);
+        match brick.insert_local_editable_front(|idx| lines[idx].is_pinned()) {
+            Ok(new) => {
+                lines[new] = tail;
+                Ok(())
+            }
+            Err(e) => {
+                // Put the tail back, which fits as it was just taken out
+                let _ = lines[cur].extend(tail.as_str());
+                Err(e.into())
+            }
+        }
+    }
+
     /// Attempts to remove a character from the local editing region
+    ///
+    /// Nothing is removed if the NEWEST local editing line is
+    /// [read-only](Line::set_read_only()), or only holds the prompt set with
+    /// [RingLine::set_local_prompt()].
     pub fn pop_local_char(&mut self) {
-        let Self { lines, brick } = self;
-        if let Some(cur) = brick.iter_local_editable_mut(lines).next() {
-            if cur.is_empty() {
+        #[cfg(feature = "undo")]
+        if self.can_pop_local() {
+            self.save_undo();
+        }
+        self.pop_local();
+    }
+
+    /// Removes a character, or an empty line, from the local editing region,
+    /// returning `false` if there was nothing that could be removed
+    fn pop_local(&mut self) -> bool {
+        let Self { lines, brick, .. } = self;
+        match brick.iter_local_editable_mut(lines).next() {
+            Some(cur) if cur.is_read_only() => false,
+            Some(cur) if cur.is_empty() => {
                 brick.pop_local_editable_front();
-            } else {
-                cur.pop();
+                true
             }
+            Some(cur) => cur.pop().is_ok(),
+            None => false,
         }
     }
 
-    /// Attempts to remove a character from the local editing region
+    /// Removes the last word from the local editing region, like Ctrl-W in a shell
+    ///
+    /// Any trailing whitespace is removed, followed by the non-whitespace characters
+    /// before it, which may span wrapped lines. Does nothing if the region is empty.
+    pub fn pop_local_word(&mut self) {
+        #[cfg(feature = "undo")]
+        if self.last_local_char().is_some() && self.can_pop_local() {
+            self.save_undo();
+        }
+        while matches!(self.last_local_char(), Some(c) if c.is_ascii_whitespace()) {
+            if !self.pop_local() {
+                return;
+            }
+        }
+        while matches!(self.last_local_char(), Some(c) if !c.is_ascii_whitespace()) {
+            if !self.pop_local() {
+                return;
+            }
+        }
+    }
+
+    /// The newest character in the local editing region, if any
+    fn last_local_char(&self) -> Option<u8> {
+        self.local_editing_chars().next_back()
+    }
+
+    /// Attempts to remove a character from the remote editing region
+    ///
+    /// Nothing is removed if the NEWEST remote editing line is
+    /// [read-only](Line::set_read_only()).
     pub fn pop_remote_char(&mut self) {
-        let Self { lines, brick } = self;
-        if let Some(cur) = brick.iter_remote_editable_mut(lines).next() {
-            if cur.is_empty() {
-                brick.pop_remote_editable_front();
-            } else {
-                cur.pop();
+        self.remote_overwrite = None;
+        let Self { lines, brick, .. } = self;
+        match brick.iter_remote_editable_mut(lines).next() {
+            Some(cur) if cur.is_read_only() => {}
+            Some(cur) if cur.is_empty() => brick.pop_remote_editable_front(),
+            Some(cur) => {
+                let _ = cur.pop();
             }
+            None => {}
         }
     }
 
-    fn get_local_first_writeable(&mut self) -> Option<&mut Line<C>> {
-        let Self { lines, brick } = self;
+    /// Returns the remote write position to the start of the NEWEST remote
+    /// editing line, like `\r` on a terminal
+    ///
+    /// Following calls to [RingLine::append_remote_char()] overwrite the line one
+    /// character at a time, and append as usual once they pass its end. This is
+    /// undone by popping or submitting remote characters.
+    pub fn remote_carriage_return(&mut self) {
+        self.remote_overwrite = Some(0);
+    }
+
+    /// Restores the local editing region to how it was before the last call to
+    /// [RingLine::append_local_char()], [RingLine::append_local()],
+    /// [RingLine::pop_local_char()], [RingLine::pop_local_word()],
+    /// [RingLine::replace_local_editing()] or
+    /// [RingLine::recall_history_into_local()]
+    ///
+    /// There is a single level of undo: once used, it is gone until the next of
+    /// those edits. Edits that fail or change nothing keep the previous undo.
+    /// Submitting the local editing region, [RingLine::clear_all()] and
+    /// [RingLine::rewrap()] also discard it. Other changes to the region, such as
+    /// [RingLine::split_local_at()] or through [RingLine::iter_local_editing_mut()],
+    /// are not tracked, and are lost on undo.
+    ///
+    /// Returns `false`, changing nothing, if there is nothing to undo, or if the
+    /// saved lines no longer fit beside the remote editing region and pinned
+    /// history.
+    #[cfg(feature = "undo")]
+    pub fn undo_local(&mut self) -> bool {
+        let saved = self.undo.iter_local_editing().len();
+        if !self.undo_valid || saved > self.local_lines_available() {
+            return false;
+        }
+        self.undo_valid = false;
+
+        self.clear_local_editing();
+        let Self {
+            lines,
+            brick,
+            on_evict,
+            undo,
+            ..
+        } = self;
+        for line in undo.iter_local_editing().rev() {
+            Self::evicting(*on_evict, lines, brick);
+            // Room was checked above
+            if let Ok(idx) = brick.insert_local_editable_front(|idx| lines[idx].is_pinned()) {
+                lines[idx].clone_from(line);
+            }
+        }
+        true
+    }
+
+    /// Would appending a valid character to the local editing region change it?
+    #[cfg(feature = "undo")]
+    fn can_push_local(&self) -> bool {
+        match self.brick.local_editable_front().map(|wr| &self.lines[wr]) {
+            Some(cur) if cur.len() < self.soft_limit => !cur.is_read_only(),
+            Some(_) if self.wrap != WrapStrategy::Wrap => false,
+            _ => self
+                .brick
+                .can_insert_local_editable(|idx| self.lines[idx].is_pinned()),
+        }
+    }
+
+    /// Would [RingLine::pop_local()] remove anything?
+    #[cfg(feature = "undo")]
+    fn can_pop_local(&self) -> bool {
+        match self.brick.local_editable_front().map(|wr| &self.lines[wr]) {
+            Some(cur) if cur.is_read_only() => false,
+            Some(cur) => cur.is_empty() || cur.len() > cur.prefix_len(),
+            None => false,
+        }
+    }
+
+    /// Saves the local editing region for [RingLine::undo_local()]
+    #[cfg(feature = "undo")]
+    fn save_undo(&mut self) {
+        let Self {
+            lines, brick, undo, ..
+        } = self;
+        undo.load(
+            brick.iter_local_editable(lines),
+            core::iter::empty(),
+            core::iter::empty(),
+        );
+        self.undo_valid = true;
+    }
+
+    /// Removes all lines from the local editing region
+    fn clear_local_editing(&mut self) {
+        while self.brick.local_editable_front().is_some() {
+            self.brick.pop_local_editable_front();
+        }
+    }
+
+    /// The number of lines the local editing region could grow to, which is every
+    /// line not used by the remote editing region or pinned history
+    fn local_lines_available(&self) -> usize {
+        let pinned = self.iter_history().filter(|l| l.is_pinned()).count();
+        L - self.iter_remote_editing().count() - pinned
+    }
+
+    /// Calls the eviction hook, if any, with the history line that the next
+    /// insert into `brick` will recycle, while it still holds its content
+    fn evicting(on_evict: Option<fn(&Line<C>)>, lines: &[Line<C>; L], brick: &Bricks<L>) {
+        let victim = on_evict.zip(brick.history_victim(|idx| lines[idx].is_pinned()));
+        if let Some((f, idx)) = victim {
+            f(&lines[idx]);
+        }
+    }
+
+    fn get_local_first_writeable(&mut self) -> Result<&mut Line<C>, RingLineError> {
+        let limit = self.soft_limit;
+        let Self {
+            lines,
+            brick,
+            on_evict,
+            wrap,
+            ..
+        } = self;
         // If empty, make a new one and return
         // If not empty, is the head writable and !full? => return
         // else, if not full make a new one and return
         // else, remove oldest, make a new one and return
         let mut new = false;
+        let mut wrapped = false;
         let wr = if let Some(wr) = brick.local_editable_front() {
             let cur = &lines[wr];
-            if cur.is_full() {
+            if cur.len() >= limit {
+                if *wrap != WrapStrategy::Wrap {
+                    return Err(RingLineError::Line(LineError::Full));
+                }
                 new = true;
-                self.brick.insert_local_editable_front().ok()?
+                wrapped = true;
+                Self::evicting(*on_evict, lines, brick);
+                brick.insert_local_editable_front(|idx| lines[idx].is_pinned())?
             } else {
                 wr
             }
         } else {
             new = true;
-            self.brick.insert_local_editable_front().ok()?
+            Self::evicting(*on_evict, lines, brick);
+            brick.insert_local_editable_front(|idx| lines[idx].is_pinned())?
         };
         let cur = &mut lines[wr];
         if new {
             cur.clear();
             cur.set_status(Source::Local);
+            cur.set_continuation(wrapped);
         }
 
-        Some(cur)
+        Ok(cur)
     }
 
-    fn get_remote_first_writeable(&mut self) -> Option<&mut Line<C>> {
-        let Self { lines, brick } = self;
+    fn get_remote_first_writeable(&mut self) -> Result<&mut Line<C>, RingLineError> {
+        let limit = self.soft_limit;
+        let Self {
+            lines,
+            brick,
+            on_evict,
+            wrap,
+            ..
+        } = self;
         // If empty, make a new one and return
         // If not empty, is the head writable and !full? => return
         // else, if not full make a new one and return
         // else, remove oldest, make a new one and return
         let mut new = false;
+        let mut wrapped = false;
         let wr = if let Some(wr) = brick.remote_editable_front() {
             let cur = &lines[wr];
-            if cur.is_full() {
+            if cur.len() >= limit {
+                if *wrap != WrapStrategy::Wrap {
+                    return Err(RingLineError::Line(LineError::Full));
+                }
                 new = true;
-                self.brick.insert_remote_editable_front().ok()?
+                wrapped = true;
+                Self::evicting(*on_evict, lines, brick);
+                brick.insert_remote_editable_front(|idx| lines[idx].is_pinned())?
             } else {
                 wr
             }
         } else {
             new = true;
-            self.brick.insert_remote_editable_front().ok()?
+            Self::evicting(*on_evict, lines, brick);
+            brick.insert_remote_editable_front(|idx| lines[idx].is_pinned())?
         };
         let cur = &mut lines[wr];
         if new {
             cur.clear();
             cur.set_status(Source::Remote);
+            cur.set_continuation(wrapped);
+        }
+
+        Ok(cur)
+    }
+}
+
+// Two ringlines are equal when each region holds the same lines, in the same
+// order, with the same text, source, and wrapping, regardless of which slots
+// back them.
+impl<const L: usize, const C: usize> PartialEq for RingLine<L, C> {
+    fn eq(&self, other: &Self) -> bool {
+        fn same<'a, const C: usize>(
+            a: impl Iterator<Item = &'a Line<C>>,
+            b: impl Iterator<Item = &'a Line<C>>,
+        ) -> bool {
+            a.map(|l| (l.as_str(), l.status(), l.is_continuation()))
+                .eq(b.map(|l| (l.as_str(), l.status(), l.is_continuation())))
         }
 
-        Some(cur)
+        same(self.iter_local_editing(), other.iter_local_editing())
+            && same(self.iter_remote_editing(), other.iter_remote_editing())
+            && same(self.iter_history(), other.iter_history())
+    }
+}
+
+impl<const L: usize, const C: usize> Default for RingLine<L, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Dumping every line over RTT is rarely what you want, so only summarize
+// how many lines are in each region.
+#[cfg(feature = "defmt")]
+impl<const L: usize, const C: usize> defmt::Format for RingLine<L, C> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "RingLine {{ local_editing: {=usize}, remote_editing: {=usize}, history: {=usize}, capacity: {=usize} }}",
+            self.iter_local_editing().count(),
+            self.iter_remote_editing().count(),
+            self.iter_history().count(),
+            L,
+        )
+    }
+}
+
+/// A line from a [RingLine], tagged with the region it currently belongs to
+///
+/// Yielded by [RingLine::iter_all()].
+#[derive(Debug)]
+pub enum RegionLine<'a, const C: usize> {
+    History(&'a Line<C>),
+    RemoteEditing(&'a Line<C>),
+    LocalEditing(&'a Line<C>),
+}
+
+impl<'a, const C: usize> RegionLine<'a, C> {
+    /// The line, regardless of which region it belongs to
+    pub fn line(&self) -> &'a Line<C> {
+        match self {
+            RegionLine::History(l) => l,
+            RegionLine::RemoteEditing(l) => l,
+            RegionLine::LocalEditing(l) => l,
+        }
+    }
+}
+
+/// Iterator over all regions of a [RingLine], see [RingLine::iter_all()]
+pub struct AllLinesIter<'a, const L: usize, const C: usize> {
+    local: LineIter<'a, L, Line<C>>,
+    remote: LineIter<'a, L, Line<C>>,
+    history: LineIter<'a, L, Line<C>>,
+}
+
+impl<'a, const L: usize, const C: usize> Iterator for AllLinesIter<'a, L, C> {
+    type Item = RegionLine<'a, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.local
+            .next()
+            .map(RegionLine::LocalEditing)
+            .or_else(|| self.remote.next().map(RegionLine::RemoteEditing))
+            .or_else(|| self.history.next().map(RegionLine::History))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.local.len() + self.remote.len() + self.history.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, const L: usize, const C: usize> ExactSizeIterator for AllLinesIter<'a, L, C> {}
+
+impl<'a, const L: usize, const C: usize> DoubleEndedIterator for AllLinesIter<'a, L, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.history
+            .next_back()
+            .map(RegionLine::History)
+            .or_else(|| self.remote.next_back().map(RegionLine::RemoteEditing))
+            .or_else(|| self.local.next_back().map(RegionLine::LocalEditing))
+    }
+}
+
+/// What appending to an editing line does once it reaches the
+/// [soft line limit](RingLine::set_soft_line_limit()), see
+/// [RingLine::set_wrap_strategy()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WrapStrategy {
+    /// Continue on a new editing line
+    Wrap,
+    /// Silently drop the character
+    Truncate,
+    /// Return [LineError::Full]
+    Reject,
+}
+
+/// What [RingLine::load_history()] does with a line containing characters that
+/// are not printable ASCII
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OnInvalid {
+    /// The whole line is left out
+    Skip,
+    /// Each invalid character is replaced with this one. If it is not valid
+    /// either, the line is left out.
+    Replace(u8),
+}
+
+/// A handle to a history line pinned by [RingLine::pin_history()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PinId(u64);
+
+impl PinId {
+    /// The sequence number of the pinned line, see [RingLine::history_by_seq()]
+    pub fn seq(&self) -> u64 {
+        self.0
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum RingLineError {
     Line(LineError),
+    /// A new line was needed, but every line is already used by the editing
+    /// regions
+    BufferExhausted,
+    /// A new line was needed, but the only lines that could be recycled are
+    /// [pinned](RingLine::pin_history())
+    AllPinned,
+    /// A new line was needed, but there are no free lines left, and history is
+    /// not recycled, see [RingLine::try_append_remote_char()]
+    NoFreeLines,
+    /// The requested history line does not exist, or is no longer in the history
+    NoSuchLine,
+    /// The requested history line did not come from the local side, see
+    /// [RingLine::recall_history_into_local()]
+    NotLocal,
 }
 
 impl From<LineError> for RingLineError {
@@ -288,7 +1813,17 @@ impl From<LineError> for RingLineError {
     }
 }
 
+impl From<BricksError> for RingLineError {
+    fn from(be: BricksError) -> Self {
+        match be {
+            BricksError::RegionFull => RingLineError::BufferExhausted,
+            BricksError::AllPinned => RingLineError::AllPinned,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LineError {
     Full,
     InvalidChar,
@@ -296,11 +1831,125 @@ pub enum LineError {
     WriteGap,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum Source {
-    Local,
-    Remote,
+    Local = 0,
+    Remote = 1,
+    /// Status or meta messages that aren't attributable to either end, such
+    /// as "connection lost". Only ever found in the history region.
+    System = 2,
+}
+
+impl Source {
+    /// Converts a tag produced by [Source::as_u8()] back into a [Source], or
+    /// returns `None` if the value is out of range
+    pub const fn from_u8(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(Source::Local),
+            1 => Some(Source::Remote),
+            2 => Some(Source::System),
+            _ => None,
+        }
+    }
+
+    /// The compact tag for this source, suitable for storage or FFI
+    pub const fn as_u8(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl TryFrom<u8> for Source {
+    /// The rejected value
+    type Error = u8;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        Self::from_u8(val).ok_or(val)
+    }
+}
+
+impl From<Source> for u8 {
+    fn from(src: Source) -> Self {
+        src.as_u8()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use core::{fmt, marker::PhantomData};
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        ser::SerializeTuple,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::RingLine;
+
+    // Serialized as a flat tuple of all `L` lines (in storage order), followed
+    // by the [Bricks](crate::bricks::Bricks) ordering information, the next
+    // sequence number, and the soft line limit.
+    impl<const L: usize, const C: usize> Serialize for RingLine<L, C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tup = serializer.serialize_tuple(L + 3)?;
+            for line in self.lines.iter() {
+                tup.serialize_element(line)?;
+            }
+            tup.serialize_element(&self.brick)?;
+            tup.serialize_element(&self.next_seq)?;
+            tup.serialize_element(&self.soft_limit)?;
+            tup.end()
+        }
+    }
+
+    struct RingLineVisitor<const L: usize, const C: usize>(PhantomData<RingLine<L, C>>);
+
+    impl<'de, const L: usize, const C: usize> Visitor<'de> for RingLineVisitor<L, C> {
+        type Value = RingLine<L, C>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} lines followed by their ordering and settings", L)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut rline = RingLine::<L, C>::new();
+            for (i, line) in rline.lines.iter_mut().enumerate() {
+                *line = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            }
+            rline.brick = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(L, &self))?;
+            rline.next_seq = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(L + 1, &self))?;
+            rline.soft_limit = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(L + 2, &self))?;
+            if rline.soft_limit == 0 || rline.soft_limit > C {
+                return Err(de::Error::custom("soft line limit out of range"));
+            }
+            Ok(rline)
+        }
+    }
+
+    impl<'de, const L: usize, const C: usize> Deserialize<'de> for RingLine<L, C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_tuple(L + 3, RingLineVisitor(PhantomData))
+        }
+    }
+}
+
+/// The ascii byte for `c`, if it is an ascii character
+fn ascii_byte(c: char) -> Result<u8, LineError> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(LineError::InvalidChar)
+    }
 }
 
 #[inline]
@@ -2,15 +2,28 @@
 //!
 //! Lines generally act as a vector of ascii characters, with a fixed upper size.
 //!
-//! At the moment, lines must be <= 255 bytes long.
+//! Lines may be at most [u16::MAX] bytes long.
 
-use core::cmp::Ordering;
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::Index,
+};
 
 use crate::{rot_right, LineError, Source};
 
+/// The used length of a [Line]
+///
+/// Stored more compactly than a `usize`. This limits the capacity of a line to
+/// [MAX_LINE_LEN], which is checked at compile time.
+type Fill = u16;
+
+/// The largest capacity that can be measured by [Fill]
+pub(crate) const MAX_LINE_LEN: usize = Fill::MAX as usize;
+
 /// A single line with fixed capacity for C characters
 ///
-/// At the moment, `C` must be <= 255.
+/// `C` may be at most [u16::MAX].
 ///
 /// This [Line] acts more or less like a small, fixed size vector of ascii characters
 ///
@@ -18,20 +31,42 @@ use crate::{rot_right, LineError, Source};
 ///
 /// [valid ascii]: u8::is_ascii()
 /// [control characters]: u8::is_ascii_control()
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Line<const C: usize> {
-    fill: u8,
+    fill: Fill,
     buf: [u8; C],
     status: Source,
+    seq: u64,
+    timestamp: Option<u64>,
+    continued: bool,
+    pinned: bool,
+    read_only: bool,
+    /// The length of the protected prefix, see [Line::prefix_len()]
+    prefix: Fill,
+    #[cfg(feature = "attrs")]
+    attr: u8,
 }
 
 impl<const C: usize> Line<C> {
+    const FITS_FILL: () = assert!(C <= MAX_LINE_LEN, "Line too long!");
+
     /// Create a new empty line
     pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_FILL;
+
         Self {
             fill: 0,
             buf: [0u8; C],
             status: Source::Local,
+            seq: 0,
+            timestamp: None,
+            continued: false,
+            pinned: false,
+            read_only: false,
+            prefix: 0,
+            #[cfg(feature = "attrs")]
+            attr: 0,
         }
     }
 
@@ -45,6 +80,138 @@ impl<const C: usize> Line<C> {
         self.status = source;
     }
 
+    /// The sequence number given to this line when it entered history (internal interface)
+    pub(crate) fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Set the sequence number (internal interface)
+    pub(crate) fn set_seq(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+
+    /// The caller-provided time this line was submitted at, if any
+    ///
+    /// See [RingLine::submit_local_editing_at()](crate::RingLine::submit_local_editing_at).
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    /// Set the timestamp (internal interface)
+    pub(crate) fn set_timestamp(&mut self, timestamp: Option<u64>) {
+        self.timestamp = timestamp;
+    }
+
+    /// Does this line continue the previous (older) one?
+    ///
+    /// This is `true` when the line was started because the previous line of the
+    /// same message was full and wrapped, and `false` for the first line of a
+    /// message.
+    pub fn is_continuation(&self) -> bool {
+        self.continued
+    }
+
+    /// Mark the line as continuing the previous one (internal interface)
+    pub(crate) fn set_continuation(&mut self, continued: bool) {
+        self.continued = continued;
+    }
+
+    /// Is this line pinned in the history, see [RingLine::pin_history()](crate::RingLine::pin_history)
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Pin or unpin the line (internal interface)
+    pub(crate) fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    /// Is this line read-only, see [Line::set_read_only()]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Make the line read-only, or writable again
+    ///
+    /// While read-only, every method that would change the contents of the line
+    /// returns [LineError::ReadOnly] instead, and [Line::trim_end()] does nothing.
+    /// This can be used to protect e.g. a prompt in an editing line. The flag is
+    /// reset when the line is recycled by a [RingLine](crate::RingLine).
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// The application-defined attribute of this line, e.g. a color index
+    ///
+    /// This is `0` unless set with [Line::set_attr()], and is reset when the line
+    /// is recycled by a [RingLine](crate::RingLine). It is not serialized.
+    #[cfg(feature = "attrs")]
+    pub fn attr(&self) -> u8 {
+        self.attr
+    }
+
+    /// Set the application-defined attribute of this line, see [Line::attr()]
+    #[cfg(feature = "attrs")]
+    pub fn set_attr(&mut self, attr: u8) {
+        self.attr = attr;
+    }
+
+    /// Returns [LineError::ReadOnly] if the line is read-only
+    fn writable(&self) -> Result<(), LineError> {
+        self.writable_from(self.prefix.into())
+    }
+
+    /// Returns [LineError::ReadOnly] if the line is read-only, or if `pos` is
+    /// within the protected prefix
+    fn writable_from(&self, pos: usize) -> Result<(), LineError> {
+        if self.read_only || pos < self.prefix_len() {
+            Err(LineError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The length of the protected prefix at the start of the line, such as a
+    /// prompt, see [RingLine::set_local_prompt()](crate::RingLine::set_local_prompt)
+    ///
+    /// Characters within the prefix can't be popped, overwritten, or inserted
+    /// before: those methods return [LineError::ReadOnly] instead.
+    pub fn prefix_len(&self) -> usize {
+        self.prefix.into()
+    }
+
+    /// Obtain the part of the line after the protected prefix as a `&str`
+    ///
+    /// This is the whole line if there is no prefix, see [Line::prefix_len()].
+    pub fn editable_str(&self) -> &str {
+        self.as_str().get(self.prefix_len()..).unwrap_or("")
+    }
+
+    /// Replace the protected prefix with `s` (internal interface)
+    ///
+    /// Nothing is changed unless the whole prefix fits, along with the rest of
+    /// the line, within `limit`.
+    pub(crate) fn set_prefix(&mut self, s: &str, limit: usize) -> Result<(), LineError> {
+        if self.read_only {
+            return Err(LineError::ReadOnly);
+        }
+        let old = self.prefix_len();
+        let len = self.len();
+        let new_len = len - old + s.len();
+        if new_len > limit.min(C) {
+            return Err(LineError::Full);
+        }
+        if !s.as_bytes().iter().copied().all(acceptable_ascii) {
+            return Err(LineError::InvalidChar);
+        }
+
+        self.buf.copy_within(old..len, s.len());
+        self.buf[..s.len()].copy_from_slice(s.as_bytes());
+        self.fill = new_len as Fill;
+        self.prefix = s.len() as Fill;
+        Ok(())
+    }
+
     /// Clear the line (internal interface)
     ///
     /// NOTE: this is private because this line should only be cleared like this
@@ -52,6 +219,15 @@ impl<const C: usize> Line<C> {
     pub(crate) fn clear(&mut self) {
         self.fill = 0;
         self.status = Source::Local;
+        self.timestamp = None;
+        self.continued = false;
+        self.pinned = false;
+        self.read_only = false;
+        self.prefix = 0;
+        #[cfg(feature = "attrs")]
+        {
+            self.attr = 0;
+        }
     }
 
     /// The currently used number of bytes in this line
@@ -69,11 +245,26 @@ impl<const C: usize> Line<C> {
         self.len() >= C
     }
 
+    /// The maximum number of characters this line can hold, `C`
+    pub const fn capacity(&self) -> usize {
+        C
+    }
+
+    /// The number of characters that can still be pushed to this line
+    pub fn remaining(&self) -> usize {
+        C - self.len()
+    }
+
     /// Pop a character from the END of the line (if any)
-    pub fn pop(&mut self) {
+    ///
+    /// Returns an error if the line is read-only, or if only the protected prefix
+    /// is left
+    pub fn pop(&mut self) -> Result<(), LineError> {
+        self.writable_from(self.len().saturating_sub(1))?;
         if self.fill != 0 {
             self.fill -= 1;
         }
+        Ok(())
     }
 
     /// Obtain the current line as a `&str`
@@ -84,12 +275,84 @@ impl<const C: usize> Line<C> {
             .unwrap_or("")
     }
 
-    pub const fn cap_u8() -> u8 {
-        if C > ((u8::MAX - 1) as usize) {
-            panic!("Too big!")
-        } else {
-            C as u8
+    /// Iterates the characters of the line
+    ///
+    /// Lines only hold printable ASCII, so each byte is exactly one [char].
+    pub fn chars(&self) -> impl DoubleEndedIterator<Item = char> + ExactSizeIterator + '_ {
+        self.as_bytes().iter().copied().map(char::from)
+    }
+
+    /// Obtain the current line as a `&[u8]`
+    ///
+    /// Unlike [Line::as_str()], this does not need to validate the contents.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len()]
+    }
+
+    /// The character at column `idx`, or `None` if it is past the end of the line
+    ///
+    /// Indexing with `line[idx]` is the same, but panics instead.
+    pub fn get(&self, idx: usize) -> Option<u8> {
+        self.as_bytes().get(idx).copied()
+    }
+
+    /// Does the line start with the given bytes?
+    ///
+    /// An empty `prefix` always matches.
+    pub const fn starts_with(&self, prefix: &[u8]) -> bool {
+        let len = self.fill as usize;
+        if prefix.len() > len {
+            return false;
         }
+        let mut i = 0;
+        while i < prefix.len() {
+            if self.buf[i] != prefix[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Does the line end with the given bytes?
+    ///
+    /// An empty `suffix` always matches.
+    pub const fn ends_with(&self, suffix: &[u8]) -> bool {
+        let len = self.fill as usize;
+        if suffix.len() > len {
+            return false;
+        }
+        let start = len - suffix.len();
+        let mut i = 0;
+        while i < suffix.len() {
+            if self.buf[start + i] != suffix[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Does the line contain the given bytes anywhere?
+    ///
+    /// An empty `needle` always matches.
+    pub const fn contains(&self, needle: &[u8]) -> bool {
+        let len = self.fill as usize;
+        if needle.len() > len {
+            return false;
+        }
+        let mut start = 0;
+        while start + needle.len() <= len {
+            let mut i = 0;
+            while i < needle.len() && self.buf[start + i] == needle[i] {
+                i += 1;
+            }
+            if i == needle.len() {
+                return true;
+            }
+            start += 1;
+        }
+        false
     }
 
     /// Extend the current line with the given string slice.
@@ -97,6 +360,7 @@ impl<const C: usize> Line<C> {
     /// Returns an error if the provided slice would not fit, or if
     /// any characters are invalid ascii or ascii control characters
     pub fn extend(&mut self, s: &str) -> Result<(), LineError> {
+        self.writable()?;
         let len = self.len();
 
         if len + s.len() > C {
@@ -106,7 +370,7 @@ impl<const C: usize> Line<C> {
             return Err(LineError::InvalidChar);
         }
         self.buf[len..][..s.len()].copy_from_slice(s.as_bytes());
-        self.fill += s.len() as u8;
+        self.fill += s.len() as Fill;
         Ok(())
     }
 
@@ -116,6 +380,7 @@ impl<const C: usize> Line<C> {
     /// end of the current line), or if the given character is invalid ascii or
     /// an ascii control character
     pub fn overwrite(&mut self, pos: usize, ovrw: u8) -> Result<(), LineError> {
+        self.writable_from(pos)?;
         if pos > self.len() || pos >= C {
             return Err(LineError::Full);
         }
@@ -128,6 +393,152 @@ impl<const C: usize> Line<C> {
         Ok(())
     }
 
+    /// Split the line at `at`, moving everything from `at` onwards into `dst`
+    ///
+    /// `dst` is cleared first, and takes the status of this line. Nothing is changed
+    /// if `at` is beyond the end of the line, or if either line is read-only, or if
+    /// `at` is within the protected prefix.
+    pub fn split_into(&mut self, at: usize, dst: &mut Line<C>) -> Result<(), LineError> {
+        self.writable_from(at)?;
+        dst.writable()?;
+        let len = self.len();
+        if at > len {
+            return Err(LineError::WriteGap);
+        }
+
+        dst.clear();
+        dst.status = self.status;
+        dst.buf[..len - at].copy_from_slice(&self.buf[at..len]);
+        dst.fill = (len - at) as Fill;
+        self.fill = at as Fill;
+        Ok(())
+    }
+
+    /// Move everything from `at` onwards into `dst`, as a wrapped continuation of
+    /// this line (internal interface)
+    ///
+    /// Unlike [Line::split_into()], this ignores protection, and `dst` takes all of
+    /// this line's metadata except its pin and sequence number. Any of the
+    /// protected prefix from `at` onwards stays protected in `dst`.
+    pub(crate) fn wrap_into(&mut self, at: usize, dst: &mut Line<C>) {
+        let len = self.len();
+        let at = at.min(len);
+        dst.buf[..len - at].copy_from_slice(&self.buf[at..len]);
+        dst.fill = (len - at) as Fill;
+        dst.status = self.status;
+        dst.timestamp = self.timestamp;
+        dst.continued = true;
+        dst.pinned = false;
+        dst.read_only = self.read_only;
+        dst.prefix = self.prefix.saturating_sub(at as Fill);
+        #[cfg(feature = "attrs")]
+        {
+            dst.attr = self.attr;
+        }
+        self.fill = at as Fill;
+        self.prefix = self.prefix.min(at as Fill);
+    }
+
+    /// Move up to `n` characters from the start of `src` onto the end of this
+    /// line, joining wrapped lines back together (internal interface)
+    ///
+    /// This ignores protection. Characters from `src`'s protected prefix stay
+    /// protected if the whole of this line is protected too.
+    pub(crate) fn pull_from(&mut self, src: &mut Line<C>, n: usize) {
+        let (len, src_len) = (self.len(), src.len());
+        let n = n.min(src_len).min(C - len);
+        self.buf[len..len + n].copy_from_slice(&src.buf[..n]);
+        src.buf.copy_within(n..src_len, 0);
+        if self.prefix == self.fill {
+            self.prefix += src.prefix.min(n as Fill);
+        }
+        src.prefix = src.prefix.saturating_sub(n as Fill);
+        self.fill += n as Fill;
+        src.fill -= n as Fill;
+    }
+
+    /// Remove any trailing spaces or tabs from the line, stopping at the protected
+    /// prefix
+    pub fn trim_end(&mut self) {
+        if self.read_only {
+            return;
+        }
+        while let [.., b' ' | b'\t'] = self.editable_str().as_bytes() {
+            self.fill -= 1;
+        }
+    }
+
+    /// Keep only the characters for which `f` returns `true`, in order, like
+    /// `Vec::retain`
+    ///
+    /// The protected prefix is always kept, and a read-only line is not changed.
+    pub fn retain(&mut self, mut f: impl FnMut(u8) -> bool) {
+        if self.read_only {
+            return;
+        }
+        let start = self.prefix_len();
+        let mut kept = start;
+        for i in start..self.len() {
+            let b = self.buf[i];
+            if f(b) {
+                self.buf[kept] = b;
+                kept += 1;
+            }
+        }
+        self.fill = kept as Fill;
+    }
+
+    /// Overwrite the given string slice, starting at the given position
+    ///
+    /// The line is extended if `s` reaches past the current end. Nothing is
+    /// written unless the whole slice can be. Returns an error if `pos` would leave
+    /// a gap (beyond the end of the current line), if the line would exceed its
+    /// capacity, or if any characters are invalid ascii or ascii control characters
+    pub fn overwrite_str(&mut self, pos: usize, s: &str) -> Result<(), LineError> {
+        self.writable_from(pos)?;
+        let len = self.len();
+        if pos > len {
+            return Err(LineError::WriteGap);
+        }
+        let end = pos + s.len();
+        if end > C {
+            return Err(LineError::Full);
+        }
+        if !s.as_bytes().iter().copied().all(acceptable_ascii) {
+            return Err(LineError::InvalidChar);
+        }
+
+        self.buf[pos..end].copy_from_slice(s.as_bytes());
+        self.fill = len.max(end) as Fill;
+        Ok(())
+    }
+
+    /// Insert the given string slice at the given position
+    ///
+    /// The tail of the line is shifted right once to make room. Nothing is written
+    /// unless the whole slice can be. Returns an error if `pos` would leave a gap
+    /// (beyond the end of the current line), if the line would exceed its capacity,
+    /// or if any characters are invalid ascii or ascii control characters
+    pub fn insert_str(&mut self, pos: usize, s: &str) -> Result<(), LineError> {
+        self.writable_from(pos)?;
+        let len = self.len();
+        if pos > len {
+            return Err(LineError::WriteGap);
+        }
+        let new_len = len + s.len();
+        if new_len > C {
+            return Err(LineError::Full);
+        }
+        if !s.as_bytes().iter().copied().all(acceptable_ascii) {
+            return Err(LineError::InvalidChar);
+        }
+
+        self.buf.copy_within(pos..len, pos + s.len());
+        self.buf[pos..pos + s.len()].copy_from_slice(s.as_bytes());
+        self.fill = new_len as Fill;
+        Ok(())
+    }
+
     /// Returns an error if the line is full
     pub fn not_full(&self) -> Result<(), LineError> {
         if self.is_full() {
@@ -142,6 +553,7 @@ impl<const C: usize> Line<C> {
     /// Returns an error if the provided character would not fit, or if
     /// the character is invalid ascii or an ascii control character
     pub fn push(&mut self, ins: u8) -> Result<(), LineError> {
+        self.writable()?;
         self.not_full()?;
         ascii_good(ins)?;
         self.buf[self.len()] = ins;
@@ -149,12 +561,33 @@ impl<const C: usize> Line<C> {
         Ok(())
     }
 
+    /// Push an ascii character to the end of the line `n` times
+    ///
+    /// If there is not room for all `n` characters, as many as fit are written and
+    /// [LineError::Full] is returned; the number written can be found by comparing
+    /// [Line::len()] before and after. Returns an error without writing anything if
+    /// the given character is invalid ascii or an ascii control character.
+    pub fn push_repeated(&mut self, c: u8, n: usize) -> Result<(), LineError> {
+        self.writable()?;
+        ascii_good(c)?;
+        let len = self.len();
+        let end = len.saturating_add(n).min(C);
+        self.buf[len..end].fill(c);
+        self.fill = end as Fill;
+        if end - len < n {
+            Err(LineError::Full)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Insert an ascii character at the given position
     ///
     /// Returns an error if the provided location would leave a gap (beyond the
     /// end of the current line), or if the given character is invalid ascii or
     /// an ascii control character, or if the line is already full
     pub fn insert(&mut self, pos: usize, ins: u8) -> Result<(), LineError> {
+        self.writable_from(pos)?;
         self.not_full()?;
 
         if pos >= C {
@@ -173,20 +606,81 @@ impl<const C: usize> Line<C> {
             Ordering::Greater => {
                 let len = self.len();
                 self.buf[len] = ins;
-                rot_right(&mut self.buf[..len + 1]);
+                rot_right(&mut self.buf[pos..len + 1]);
                 self.fill += 1;
                 Ok(())
             }
-            Ordering::Less => return Err(LineError::WriteGap), // trying to insert AFTER the "tip"
+            Ordering::Less => Err(LineError::WriteGap), // trying to insert AFTER the "tip"
         }
     }
 }
 
+impl<const C: usize> Default for Line<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Lines are equal when they hold the same text from the same source. Only the
+// used portion of the buffer is compared, stale bytes past the fill level are
+// ignored, as is all other metadata, such as sequence numbers.
+impl<const C: usize> PartialEq for Line<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes() && self.status == other.status
+    }
+}
+
+impl<const C: usize> Eq for Line<C> {}
+
+// Hashes only the used portion of the buffer and the source. Lines that
+// compare equal always share both, so this stays consistent with `PartialEq`.
+impl<const C: usize> Hash for Line<C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+        self.status.hash(state);
+    }
+}
+
+// Panics past the end of the line, not just past its capacity, like a `Vec`
+impl<const C: usize> Index<usize> for Line<C> {
+    type Output = u8;
+
+    fn index(&self, idx: usize) -> &u8 {
+        &self.as_bytes()[idx]
+    }
+}
+
+/// Compares only the text of the line, ignoring its source and other metadata
+impl<const C: usize> PartialEq<str> for Line<C> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+/// Compares only the text of the line, ignoring its source and other metadata
+impl<const C: usize> PartialEq<&str> for Line<C> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const C: usize> defmt::Format for Line<C> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Line {{ status: {}, text: {=str} }}",
+            self.status,
+            self.as_str()
+        )
+    }
+}
+
 fn acceptable_ascii(c: u8) -> bool {
     c.is_ascii() && !c.is_ascii_control()
 }
 
-fn ascii_good(c: u8) -> Result<(), LineError> {
+pub(crate) fn ascii_good(c: u8) -> Result<(), LineError> {
     if acceptable_ascii(c) {
         Ok(())
     } else {
@@ -194,9 +688,114 @@ fn ascii_good(c: u8) -> Result<(), LineError> {
     }
 }
 
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use core::fmt;
+
+    use serde::{
+        de::{self, SeqAccess, Unexpected, Visitor},
+        ser::SerializeTuple,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::{Fill, Line};
+    use crate::Source;
+
+    // Serialized as a `(Source, u64, Option<u64>, bool, &str, u16)` tuple, so only
+    // the used portion of the buffer is stored. The last element is the length of
+    // the protected prefix.
+    impl<const C: usize> Serialize for Line<C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tup = serializer.serialize_tuple(6)?;
+            tup.serialize_element(&self.status)?;
+            tup.serialize_element(&self.seq)?;
+            tup.serialize_element(&self.timestamp)?;
+            tup.serialize_element(&self.continued)?;
+            tup.serialize_element(self.as_str())?;
+            tup.serialize_element(&self.prefix)?;
+            tup.end()
+        }
+    }
+
+    /// Deserializes just the text portion of a line
+    struct LineText<const C: usize>(Line<C>);
+
+    struct LineTextVisitor<const C: usize>;
+
+    impl<'de, const C: usize> Visitor<'de> for LineTextVisitor<C> {
+        type Value = LineText<C>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {} printable ascii characters", C)
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            let mut line = Line::new();
+            line.extend(v)
+                .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))?;
+            Ok(LineText(line))
+        }
+    }
+
+    impl<'de, const C: usize> Deserialize<'de> for LineText<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_str(LineTextVisitor)
+        }
+    }
+
+    struct LineVisitor<const C: usize>;
+
+    impl<'de, const C: usize> Visitor<'de> for LineVisitor<C> {
+        type Value = Line<C>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a (source, sequence, timestamp, continued, text, prefix) tuple")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let status: Source = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let line_seq: u64 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            let timestamp: Option<u64> = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+            let continued: bool = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+            let LineText(mut line) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(4, &self))?;
+            let prefix: Fill = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(5, &self))?;
+            if usize::from(prefix) > line.len() {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(prefix.into()),
+                    &"a prefix no longer than the text",
+                ));
+            }
+            line.prefix = prefix;
+            line.set_status(status);
+            line.set_seq(line_seq);
+            line.set_timestamp(timestamp);
+            line.set_continuation(continued);
+            Ok(line)
+        }
+    }
+
+    impl<'de, const C: usize> Deserialize<'de> for Line<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_tuple(6, LineVisitor)
+        }
+    }
+}
+
 #[cfg(test)]
 mod line_tests {
-    use crate::LineError;
+    use crate::{LineError, Source};
 
     use super::Line;
 
@@ -211,6 +810,98 @@ mod line_tests {
     #[test]
     fn smoke_ring() {}
 
+    #[test]
+    fn as_bytes() {
+        let mut line = Line::<4>::new();
+        assert_eq!(line.as_bytes(), b"");
+        line.extend("abcd").unwrap();
+        assert_eq!(line.as_bytes(), b"abcd");
+        line.pop().unwrap();
+        assert_eq!(line.as_bytes(), b"abc");
+        assert_eq!(line.as_bytes(), line.as_str().as_bytes());
+    }
+
+    #[test]
+    fn push_repeated() {
+        let mut line = Line::<8>::new();
+        line.push_repeated(b'-', 0).unwrap();
+        assert_eq!(line.as_str(), "");
+        line.push(b'[').unwrap();
+        line.push_repeated(b'=', 3).unwrap();
+        assert_eq!(line.as_str(), "[===");
+        assert_eq!(line.push_repeated(b'\n', 1), Err(LineError::InvalidChar));
+        assert_eq!(line.as_str(), "[===");
+        assert_eq!(line.push_repeated(b' ', 10), Err(LineError::Full));
+        assert_eq!(line.as_str(), "[===    ");
+        assert_eq!(line.push_repeated(b' ', 1), Err(LineError::Full));
+        line.push_repeated(b' ', 0).unwrap();
+    }
+
+    #[test]
+    fn overwrite_str() {
+        let mut line = Line::<8>::new();
+        line.extend("cpu: 10").unwrap();
+        line.overwrite_str(5, "99").unwrap();
+        assert_eq!(line.as_str(), "cpu: 99");
+        line.overwrite_str(5, "100").unwrap();
+        assert_eq!(line.as_str(), "cpu: 100");
+        line.overwrite_str(0, "").unwrap();
+        assert_eq!(line.as_str(), "cpu: 100");
+
+        // Nothing is written on error
+        assert_eq!(line.overwrite_str(5, "1000"), Err(LineError::Full));
+        assert_eq!(line.overwrite_str(0, "mem\n"), Err(LineError::InvalidChar));
+        assert_eq!(line.as_str(), "cpu: 100");
+
+        line.clear();
+        assert_eq!(line.overwrite_str(1, "a"), Err(LineError::WriteGap));
+        line.overwrite_str(0, "abc").unwrap();
+        line.overwrite_str(3, "d").unwrap();
+        assert_eq!(line.as_str(), "abcd");
+    }
+
+    #[test]
+    fn insert_str() {
+        let mut line = Line::<10>::new();
+        line.insert_str(0, "ad").unwrap();
+        line.insert_str(1, "bc").unwrap();
+        assert_eq!(line.as_str(), "abcd");
+        line.insert_str(0, "__").unwrap();
+        assert_eq!(line.as_str(), "__abcd");
+        line.insert_str(6, "ef").unwrap();
+        assert_eq!(line.as_str(), "__abcdef");
+        line.insert_str(3, "").unwrap();
+        assert_eq!(line.as_str(), "__abcdef");
+
+        assert_eq!(line.insert_str(9, "g"), Err(LineError::WriteGap));
+        assert_eq!(line.insert_str(2, "\n"), Err(LineError::InvalidChar));
+        assert_eq!(line.insert_str(2, "xyz"), Err(LineError::Full));
+        assert_eq!(line.as_str(), "__abcdef");
+        line.insert_str(2, "xy").unwrap();
+        assert_eq!(line.as_str(), "__xyabcdef");
+        assert_eq!(line.insert_str(10, "!"), Err(LineError::Full));
+    }
+
+    #[test]
+    fn trim_end() {
+        let mut line = Line::<10>::new();
+        line.extend("hello   ").unwrap();
+        line.trim_end();
+        assert_eq!(line.as_str(), "hello");
+        line.trim_end();
+        assert_eq!(line.as_str(), "hello");
+
+        let mut line = Line::<10>::new();
+        line.extend("  a b  ").unwrap();
+        line.trim_end();
+        assert_eq!(line.as_str(), "  a b");
+
+        let mut line = Line::<10>::new();
+        line.extend("    ").unwrap();
+        line.trim_end();
+        assert!(line.is_empty());
+    }
+
     #[test]
     fn smoke_line() {
         let mut line = Line::<10>::new();
@@ -261,10 +952,10 @@ mod line_tests {
         line.extend("world").unwrap();
         assert_eq!(line.as_str(), "helloworld");
 
-        line.pop();
+        line.pop().unwrap();
         assert_eq!(line.as_str(), "helloworl");
 
-        line.pop();
+        line.pop().unwrap();
         assert_eq!(line.as_str(), "hellowor");
 
         line.clear();
@@ -279,4 +970,283 @@ mod line_tests {
         assert_eq!(line.extend("Späti").unwrap_err(), LineError::InvalidChar);
         assert_eq!(line.as_str(), "");
     }
+
+    #[test]
+    fn read_only() {
+        let mut line = Line::<8>::new();
+        line.extend("> ").unwrap();
+        line.set_read_only(true);
+        assert!(line.is_read_only());
+
+        assert_eq!(line.push(b'a'), Err(LineError::ReadOnly));
+        assert_eq!(line.push_repeated(b'a', 2), Err(LineError::ReadOnly));
+        assert_eq!(line.insert(0, b'a'), Err(LineError::ReadOnly));
+        assert_eq!(line.insert_str(0, "a"), Err(LineError::ReadOnly));
+        assert_eq!(line.overwrite(0, b'a'), Err(LineError::ReadOnly));
+        assert_eq!(line.overwrite_str(0, "a"), Err(LineError::ReadOnly));
+        assert_eq!(line.extend("a"), Err(LineError::ReadOnly));
+        assert_eq!(line.pop(), Err(LineError::ReadOnly));
+        line.trim_end();
+        assert_eq!(line.as_str(), "> ");
+
+        line.set_read_only(false);
+        line.pop().unwrap();
+        assert_eq!(line.as_str(), ">");
+    }
+
+    #[test]
+    fn prefix() {
+        let mut line = Line::<8>::new();
+        line.extend("ab").unwrap();
+        line.set_prefix("$ ", 8).unwrap();
+        assert_eq!(line.as_str(), "$ ab");
+        assert_eq!(line.prefix_len(), 2);
+        assert_eq!(line.editable_str(), "ab");
+
+        assert_eq!(line.overwrite(1, b'x'), Err(LineError::ReadOnly));
+        assert_eq!(line.insert(1, b'x'), Err(LineError::ReadOnly));
+        line.insert(2, b'x').unwrap();
+        assert_eq!(line.as_str(), "$ xab");
+
+        line.pop().unwrap();
+        line.pop().unwrap();
+        line.pop().unwrap();
+        assert_eq!(line.pop(), Err(LineError::ReadOnly));
+        assert_eq!(line.as_str(), "$ ");
+        line.trim_end();
+        assert_eq!(line.as_str(), "$ ");
+
+        assert_eq!(line.set_prefix("123456789", 8), Err(LineError::Full));
+        line.set_prefix(">", 8).unwrap();
+        assert_eq!(line.as_str(), ">");
+        line.clear();
+        assert_eq!(line.prefix_len(), 0);
+    }
+
+    #[test]
+    fn chars() {
+        let mut line = Line::<8>::new();
+        assert_eq!(line.chars().next(), None);
+        line.extend("a b~").unwrap();
+        assert_eq!(line.chars().collect::<String>(), "a b~");
+        assert_eq!(line.chars().len(), 4);
+        assert_eq!(line.chars().next_back(), Some('~'));
+    }
+
+    #[test]
+    fn eq_str() {
+        let mut line = Line::<8>::new();
+        assert!(line != "quit");
+        line.extend("quit").unwrap();
+        line.set_status(Source::Remote);
+        assert!(line == "quit");
+        assert!(line == *"quit");
+        assert!(line != "quit ");
+        assert!(line != "qui");
+    }
+
+    #[test]
+    fn split_into() {
+        let mut line = Line::<8>::new();
+        let mut dst = Line::<8>::new();
+        dst.extend("old").unwrap();
+        line.extend("abcd").unwrap();
+        line.set_prefix("$ ", 8).unwrap();
+        line.set_status(Source::Remote);
+
+        assert_eq!(line.split_into(7, &mut dst), Err(LineError::WriteGap));
+        line.split_into(4, &mut dst).unwrap();
+        assert_eq!(line.as_str(), "$ ab");
+        assert_eq!(dst.as_str(), "cd");
+        assert_eq!(dst.status(), Source::Remote);
+
+        line.split_into(4, &mut dst).unwrap();
+        assert_eq!(line.as_str(), "$ ab");
+        assert!(dst.is_empty());
+
+        assert_eq!(line.split_into(1, &mut dst), Err(LineError::ReadOnly));
+        dst.set_read_only(true);
+        assert_eq!(line.split_into(3, &mut dst), Err(LineError::ReadOnly));
+        assert_eq!(line.as_str(), "$ ab");
+    }
+
+    #[test]
+    fn wrap_into_and_pull_from() {
+        let mut line = Line::<8>::new();
+        let mut dst = Line::<8>::new();
+        line.extend("abcd").unwrap();
+        line.set_prefix(">>> ", 8).unwrap();
+        line.set_status(Source::Remote);
+
+        // The prompt is split too, and stays protected
+        line.wrap_into(2, &mut dst);
+        assert_eq!(line.as_str(), ">>");
+        assert_eq!(line.prefix_len(), 2);
+        assert_eq!(dst.as_str(), "> abcd");
+        assert_eq!(dst.prefix_len(), 2);
+        assert_eq!(dst.status(), Source::Remote);
+        assert!(dst.is_continuation());
+
+        line.pull_from(&mut dst, 3);
+        assert_eq!(line.as_str(), ">>> a");
+        assert_eq!(line.prefix_len(), 4);
+        assert_eq!(dst.as_str(), "bcd");
+        assert_eq!(dst.prefix_len(), 0);
+
+        // Only up to the capacity, and what there is
+        line.pull_from(&mut dst, 8);
+        assert_eq!(line.as_str(), ">>> abcd");
+        assert!(dst.is_empty());
+    }
+
+    #[test]
+    fn eq_ignores_stale_bytes() {
+        let mut a = Line::<8>::new();
+        a.extend("hello").unwrap();
+        a.clear();
+        a.extend("hi").unwrap();
+
+        let mut b = Line::<8>::new();
+        b.extend("hi").unwrap();
+        assert_eq!(a, b);
+
+        b.push(b'!').unwrap();
+        assert_ne!(a, b);
+        b.pop().unwrap();
+        assert_eq!(a, b);
+
+        b.set_status(Source::Remote);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn hash_ignores_stale_bytes() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash_of(line: &Line<8>) -> u64 {
+            let mut h = DefaultHasher::new();
+            line.hash(&mut h);
+            h.finish()
+        }
+
+        let mut a = Line::<8>::new();
+        a.extend("hello").unwrap();
+        a.clear();
+        a.extend("hi").unwrap();
+
+        let mut b = Line::<8>::new();
+        b.extend("hi").unwrap();
+
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        b.set_status(Source::Remote);
+        assert_ne!(hash_of(&a), hash_of(&b));
+    }
+
+    #[cfg(feature = "attrs")]
+    #[test]
+    fn attr() {
+        let mut line = Line::<8>::new();
+        assert_eq!(line.attr(), 0);
+        line.set_attr(3);
+        assert_eq!(line.attr(), 3);
+        line.clear();
+        assert_eq!(line.attr(), 0);
+    }
+
+    #[test]
+    fn retain() {
+        let mut line = Line::<8>::new();
+        line.extend("abcdefg").unwrap();
+        let mut keep = false;
+        line.retain(|_| {
+            keep = !keep;
+            keep
+        });
+        assert_eq!(line.as_str(), "aceg");
+
+        line.retain(|_| false);
+        assert!(line.is_empty());
+
+        line.set_prefix("$ ", 8).unwrap();
+        line.extend("a1b2").unwrap();
+        line.retain(|b| !b.is_ascii_digit() && b != b' ');
+        assert_eq!(line.as_str(), "$ ab");
+
+        line.set_read_only(true);
+        line.retain(|_| false);
+        assert_eq!(line.as_str(), "$ ab");
+    }
+
+    #[test]
+    fn index() {
+        let mut line = Line::<8>::new();
+        line.extend("hello").unwrap();
+        line.pop().unwrap();
+        assert_eq!(line[0], b'h');
+        assert_eq!(line[3], b'l');
+        assert_eq!(line.get(3), Some(b'l'));
+        assert_eq!(line.get(4), None);
+        assert_eq!(line.get(8), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_past_end() {
+        let mut line = Line::<8>::new();
+        line.extend("hi").unwrap();
+        let _ = line[2];
+    }
+
+    #[test]
+    fn capacity() {
+        let mut line = Line::<4>::new();
+        assert_eq!(line.capacity(), 4);
+        assert_eq!(line.remaining(), 4);
+        line.extend("abc").unwrap();
+        assert_eq!(line.remaining(), 1);
+        line.push(b'd').unwrap();
+        assert_eq!(line.remaining(), 0);
+        assert!(line.is_full());
+    }
+
+    #[test]
+    fn queries() {
+        let mut line = Line::<10>::new();
+        assert!(line.starts_with(b""));
+        assert!(line.ends_with(b""));
+        assert!(line.contains(b""));
+        assert!(!line.starts_with(b"a"));
+        assert!(!line.ends_with(b"a"));
+        assert!(!line.contains(b"a"));
+
+        line.extend("hello").unwrap();
+        assert!(line.starts_with(b""));
+        assert!(line.starts_with(b"he"));
+        assert!(line.starts_with(b"hello"));
+        assert!(!line.starts_with(b"hello!"));
+        assert!(!line.starts_with(b"lo"));
+
+        assert!(line.ends_with(b""));
+        assert!(line.ends_with(b"lo"));
+        assert!(line.ends_with(b"hello"));
+        assert!(!line.ends_with(b"!hello"));
+        assert!(!line.ends_with(b"he"));
+
+        assert!(line.contains(b""));
+        assert!(line.contains(b"h"));
+        assert!(line.contains(b"ell"));
+        assert!(line.contains(b"o"));
+        assert!(line.contains(b"hello"));
+        assert!(!line.contains(b"hello!"));
+        assert!(!line.contains(b"elo"));
+
+        // Stale bytes past the end of the line are never matched
+        line.pop().unwrap();
+        line.pop().unwrap();
+        assert!(!line.ends_with(b"lo"));
+        assert!(!line.contains(b"llo"));
+        assert!(line.ends_with(b"hel"));
+    }
 }
@@ -0,0 +1,127 @@
+//! # Remote Sink
+//!
+//! An [embedded_io::Write] adapter that feeds bytes into the remote side of a
+//! [RingLine], so it can be used directly as the destination of a serial port or
+//! any other byte stream.
+
+use embedded_io::{ErrorKind, ErrorType, Write};
+
+use crate::{AnsiFilter, LineError, RingLine, RingLineError};
+
+/// A byte sink for the remote side of a [RingLine], see [RingLine::remote_sink()]
+///
+/// Bytes are handled as a terminal would:
+///
+/// * `\n` submits the remote editing region
+/// * `\r` returns to the start of the line, see [RingLine::remote_carriage_return()]
+/// * `\x08` (backspace) removes the last character
+/// * anything else is appended to the remote editing region
+///
+/// Other control characters, such as `\t` or BEL, and bytes that are not ascii,
+/// are dropped and counted, see [RemoteSink::dropped()]. ANSI escape sequences
+/// can be dropped first, see [RemoteSink::strip_ansi()].
+pub struct RemoteSink<'a, const L: usize, const C: usize> {
+    rline: &'a mut RingLine<L, C>,
+    ansi: Option<&'a mut AnsiFilter>,
+    dropped: usize,
+}
+
+impl<const L: usize, const C: usize> RingLine<L, C> {
+    /// Borrow the remote side of this ringline as an [embedded_io::Write] sink
+    pub fn remote_sink(&mut self) -> RemoteSink<'_, L, C> {
+        RemoteSink {
+            rline: self,
+            ansi: None,
+            dropped: 0,
+        }
+    }
+}
+
+impl<'a, const L: usize, const C: usize> RemoteSink<'a, L, C> {
+    /// Drop ANSI escape sequences, such as color codes, before they reach the
+    /// [RingLine]
+    ///
+    /// The `filter` is borrowed rather than owned, so that a sequence split
+    /// across writes to different sinks is still recognized.
+    pub fn strip_ansi(mut self, filter: &'a mut AnsiFilter) -> Self {
+        self.ansi = Some(filter);
+        self
+    }
+
+    /// The number of bytes dropped by this sink because they can't be stored in a
+    /// line, not counting ANSI escape sequences
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn feed(&mut self, byte: u8) -> Result<(), RingLineError> {
+        let byte = match self.ansi.as_mut() {
+            Some(filter) => match filter.filter(byte) {
+                Some(b) => b,
+                None => return Ok(()),
+            },
+            None => byte,
+        };
+        match byte {
+            b'\n' => {
+                self.rline.submit_remote_editing();
+                Ok(())
+            }
+            b'\r' => {
+                self.rline.remote_carriage_return();
+                Ok(())
+            }
+            0x08 => {
+                self.rline.pop_remote_char();
+                Ok(())
+            }
+            c => match self.rline.append_remote_char(c) {
+                Err(RingLineError::Line(LineError::InvalidChar)) => {
+                    self.dropped += 1;
+                    Ok(())
+                }
+                res => res,
+            },
+        }
+    }
+}
+
+impl embedded_io::Error for RingLineError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            RingLineError::Line(LineError::Full) => ErrorKind::WriteZero,
+            RingLineError::Line(LineError::InvalidChar) => ErrorKind::InvalidData,
+            RingLineError::Line(_) => ErrorKind::Other,
+            RingLineError::BufferExhausted
+            | RingLineError::AllPinned
+            | RingLineError::NoFreeLines => ErrorKind::WriteZero,
+            RingLineError::NoSuchLine | RingLineError::NotLocal => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl<'a, const L: usize, const C: usize> ErrorType for RemoteSink<'a, L, C> {
+    type Error = RingLineError;
+}
+
+impl<'a, const L: usize, const C: usize> Write for RemoteSink<'a, L, C> {
+    /// Accepts bytes until one can't be stored, e.g. once the remote editing
+    /// region is full
+    ///
+    /// Invalid bytes are dropped rather than rejected. The error is only returned
+    /// if the very first byte is rejected, otherwise the number of bytes accepted
+    /// so far is returned, and the rejected byte will be the first byte of the next
+    /// call.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for (i, byte) in buf.iter().enumerate() {
+            if let Err(e) = self.feed(*byte) {
+                return if i == 0 { Err(e) } else { Ok(i) };
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+//! # Snapshot
+//!
+//! A [SnapshotBuf] is a caller-provided copy of the display state of a
+//! [RingLine](crate::RingLine). Once filled by [RingLine::snapshot()](crate::RingLine::snapshot),
+//! it no longer borrows the [RingLine](crate::RingLine), so rendering can happen from the
+//! snapshot while new input is appended to the original.
+//!
+//! Unlike the [RingLine](crate::RingLine) itself, lines in a snapshot are stored in
+//! display order, so no [Bricks](crate::bricks::Bricks) indirection is needed.
+//!
+//! A [DiffResult] from [RingLine::diff()](crate::RingLine::diff) compares the
+//! [RingLine](crate::RingLine) against an earlier snapshot, so that only lines
+//! that have changed since then need to be redrawn.
+
+use core::slice::Iter;
+
+use crate::Line;
+
+/// Storage for a copy of the ordered lines of a [RingLine](crate::RingLine)
+///
+/// Like [RingLine](crate::RingLine), this does not allocate, and takes the same
+/// amount of space as the [RingLine](crate::RingLine) it is a snapshot of.
+#[derive(Debug, Clone)]
+pub struct SnapshotBuf<const L: usize, const C: usize> {
+    lines: [Line<C>; L],
+    local_editing_end: usize,  //  0..le
+    remote_editing_end: usize, // le..re
+    history_end: usize,        // re..hi
+}
+
+impl<const L: usize, const C: usize> SnapshotBuf<L, C> {
+    const ONELINE: Line<C> = Line::<C>::new();
+    const INIT: [Line<C>; L] = [Self::ONELINE; L];
+
+    /// Create a new, empty snapshot buffer
+    pub const fn new() -> Self {
+        Self {
+            lines: Self::INIT,
+            local_editing_end: 0,
+            remote_editing_end: 0,
+            history_end: 0,
+        }
+    }
+
+    /// Replace the contents of the snapshot with the given regions, each NEWEST to OLDEST
+    pub(crate) fn load<'a>(
+        &mut self,
+        local: impl Iterator<Item = &'a Line<C>>,
+        remote: impl Iterator<Item = &'a Line<C>>,
+        history: impl Iterator<Item = &'a Line<C>>,
+    ) {
+        let mut idx = 0;
+        for src in local {
+            self.lines[idx].clone_from(src);
+            idx += 1;
+        }
+        self.local_editing_end = idx;
+        for src in remote {
+            self.lines[idx].clone_from(src);
+            idx += 1;
+        }
+        self.remote_editing_end = idx;
+        for src in history {
+            self.lines[idx].clone_from(src);
+            idx += 1;
+        }
+        self.history_end = idx;
+    }
+
+    /// Compare the given regions, each NEWEST to OLDEST, against the snapshot
+    pub(crate) fn diff<'a>(
+        &self,
+        local: impl Iterator<Item = &'a Line<C>>,
+        remote: impl Iterator<Item = &'a Line<C>>,
+        history: impl Iterator<Item = &'a Line<C>>,
+    ) -> DiffResult<L> {
+        let mut result = DiffResult {
+            added: 0,
+            evicted: 0,
+            modified: [false; L],
+            editing_changed: !local.eq(self.iter_local_editing())
+                || !remote.eq(self.iter_remote_editing()),
+        };
+
+        // Both histories are sorted by decreasing sequence number, so walk them
+        // together, matching up lines with the same sequence number. Lines split
+        // off by a rewrap are out of order, but numbered after every line in the
+        // snapshot, so are counted as added wherever they are.
+        let mut cur = history.enumerate().peekable();
+        let mut old = self.iter_history().peekable();
+        loop {
+            match (cur.peek(), old.peek()) {
+                (Some((idx, c)), Some(o)) if c.seq() == o.seq() => {
+                    result.modified[*idx] = c != o;
+                    cur.next();
+                    old.next();
+                }
+                (Some((_, c)), Some(o)) if c.seq() < o.seq() => {
+                    result.evicted += 1;
+                    old.next();
+                }
+                (Some(_), _) => {
+                    result.added += 1;
+                    cur.next();
+                }
+                (None, Some(_)) => {
+                    result.evicted += 1;
+                    old.next();
+                }
+                (None, None) => break,
+            }
+        }
+        result
+    }
+
+    /// Iterates all "historical" lines in the snapshot, NEWEST to OLDEST
+    ///
+    /// See [RingLine::iter_history()](crate::RingLine::iter_history).
+    pub fn iter_history(&self) -> Iter<'_, Line<C>> {
+        self.lines[self.remote_editing_end..self.history_end].iter()
+    }
+
+    /// Iterates the lines being edited by the remote end in the snapshot, NEWEST to OLDEST
+    ///
+    /// See [RingLine::iter_remote_editing()](crate::RingLine::iter_remote_editing).
+    pub fn iter_remote_editing(&self) -> Iter<'_, Line<C>> {
+        self.lines[self.local_editing_end..self.remote_editing_end].iter()
+    }
+
+    /// Iterates the lines being edited by the local end in the snapshot, NEWEST to OLDEST
+    ///
+    /// See [RingLine::iter_local_editing()](crate::RingLine::iter_local_editing).
+    pub fn iter_local_editing(&self) -> Iter<'_, Line<C>> {
+        self.lines[..self.local_editing_end].iter()
+    }
+}
+
+impl<const L: usize, const C: usize> Default for SnapshotBuf<L, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The differences between a [RingLine](crate::RingLine) and an earlier [SnapshotBuf]
+///
+/// History lines are matched up by their sequence number, see
+/// [RingLine::iter_history_seq()](crate::RingLine::iter_history_seq). As lines only
+/// enter the history at the NEWEST end, and are evicted from the OLDEST end, the
+/// added lines are those at history indexes `0..added()`, except for lines split
+/// off by [RingLine::rewrap()](crate::RingLine::rewrap), which count as added
+/// wherever they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffResult<const L: usize> {
+    added: usize,
+    evicted: usize,
+    modified: [bool; L],
+    editing_changed: bool,
+}
+
+impl<const L: usize> DiffResult<L> {
+    /// The number of history lines that were not in the snapshot
+    pub fn added(&self) -> usize {
+        self.added
+    }
+
+    /// The number of history lines in the snapshot that have since been evicted
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Was the history line at `idx`, where `0` is the NEWEST line, in the snapshot
+    /// but with different contents or status?
+    pub fn is_modified(&self, idx: usize) -> bool {
+        self.modified.get(idx).copied().unwrap_or(false)
+    }
+
+    /// Iterates the indexes of all modified history lines, NEWEST to OLDEST
+    pub fn iter_modified(&self) -> impl Iterator<Item = usize> + '_ {
+        self.modified
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, modified)| modified.then_some(idx))
+    }
+
+    /// Has either editing region changed at all?
+    pub fn editing_changed(&self) -> bool {
+        self.editing_changed
+    }
+
+    /// Is everything the same as in the snapshot?
+    pub fn is_unchanged(&self) -> bool {
+        self.added == 0
+            && self.evicted == 0
+            && !self.editing_changed
+            && self.iter_modified().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::SnapshotBuf;
+    use crate::{RingLine, Source};
+
+    #[test]
+    fn smoke() {
+        let mut rline = RingLine::<4, 8>::new();
+        let mut snap = SnapshotBuf::<4, 8>::new();
+
+        rline.snapshot(&mut snap);
+        assert_eq!(snap.iter_history().count(), 0);
+        assert_eq!(snap.iter_local_editing().count(), 0);
+        assert_eq!(snap.iter_remote_editing().count(), 0);
+
+        b"remote"
+            .iter()
+            .for_each(|c| rline.append_remote_char(*c).unwrap());
+        rline.submit_remote_editing();
+        b"local wraps"
+            .iter()
+            .for_each(|c| rline.append_local_char(*c).unwrap());
+        b"typing"
+            .iter()
+            .for_each(|c| rline.append_remote_char(*c).unwrap());
+        rline.snapshot(&mut snap);
+
+        // The snapshot is unaffected by later changes
+        rline.submit_local_editing();
+        rline.submit_remote_editing();
+
+        let hist = snap.iter_history().collect::<Vec<_>>();
+        assert_eq!(hist.len(), 1);
+        assert_eq!(hist[0].as_str(), "remote");
+        assert_eq!(hist[0].status(), Source::Remote);
+        let remote = snap.iter_remote_editing().collect::<Vec<_>>();
+        assert_eq!(remote.len(), 1);
+        assert_eq!(remote[0].as_str(), "typing");
+        let local = snap.iter_local_editing().rev().collect::<Vec<_>>();
+        assert_eq!(local.len(), 2);
+        assert_eq!(local[0].as_str(), "local wr");
+        assert_eq!(local[1].as_str(), "aps");
+
+        // Reloading replaces the old contents entirely
+        rline.snapshot(&mut snap);
+        assert_eq!(snap.iter_history().count(), 4);
+        assert_eq!(snap.iter_local_editing().count(), 0);
+        assert_eq!(snap.iter_remote_editing().count(), 0);
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::SnapshotBuf;
+    use crate::{RingLine, Source};
+
+    #[test]
+    fn added_evicted_modified() {
+        let mut rline = RingLine::<4, 8>::new();
+        let mut snap = SnapshotBuf::<4, 8>::new();
+        rline.append_local_line("a").unwrap();
+        rline.append_local_line("b").unwrap();
+        rline.append_local_line("c").unwrap();
+        rline.snapshot(&mut snap);
+        assert!(rline.diff(&snap).is_unchanged());
+
+        // Typing only changes the editing region
+        rline.append_local_char(b'x').unwrap();
+        let diff = rline.diff(&snap);
+        assert!(diff.editing_changed());
+        assert_eq!((diff.added(), diff.evicted()), (0, 0));
+        rline.pop_local_char();
+        rline.pop_local_char();
+        assert!(rline.diff(&snap).is_unchanged());
+
+        // Two new lines need room, so "a" is evicted
+        rline.append_local_line("d").unwrap();
+        rline.append_local_line("e").unwrap();
+        rline.set_history_status(2, Source::System).unwrap();
+        let diff = rline.diff(&snap);
+        assert_eq!(diff.added(), 2);
+        assert_eq!(diff.evicted(), 1);
+        assert!(!diff.editing_changed());
+        assert_eq!(diff.iter_modified().collect::<Vec<_>>(), [2]);
+        assert!(diff.is_modified(2));
+        assert!(!diff.is_modified(3));
+        assert!(!diff.is_modified(100));
+    }
+}
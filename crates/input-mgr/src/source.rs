@@ -0,0 +1,100 @@
+//! # Ring Sources
+//!
+//! [RingSource] is the read-only view of a ring of lines that a display needs:
+//! the three regions, each NEWEST to OLDEST, made up of [DisplayLine]s. It is
+//! implemented by [RingLine], and can be implemented by other storage, or by a
+//! test double, so that drawing code doesn't need to know which one it has.
+
+use crate::{Line, LineIter, RingLine, Source};
+
+/// A single line, as needed to display it
+pub trait DisplayLine {
+    /// The text of the line
+    fn as_str(&self) -> &str;
+
+    /// The source of the line, either Local, Remote, or System
+    fn status(&self) -> Source;
+}
+
+/// The lines of a ring, in display order
+///
+/// Each iterator yields lines NEWEST to OLDEST, and can be reversed to obtain
+/// them OLDEST to NEWEST. See [RingLine::iter_history()] and friends.
+pub trait RingSource {
+    /// The type of a single line
+    type Line: DisplayLine;
+
+    /// The iterator returned for each region
+    type Iter<'a>: DoubleEndedIterator<Item = &'a Self::Line> + ExactSizeIterator
+    where
+        Self: 'a;
+
+    /// Iterates all "historical" lines, NEWEST to OLDEST
+    fn iter_history(&self) -> Self::Iter<'_>;
+
+    /// Iterates the local editing region, NEWEST to OLDEST
+    fn iter_local_editing(&self) -> Self::Iter<'_>;
+
+    /// Iterates the remote editing region, NEWEST to OLDEST
+    fn iter_remote_editing(&self) -> Self::Iter<'_>;
+}
+
+impl<const C: usize> DisplayLine for Line<C> {
+    fn as_str(&self) -> &str {
+        Line::as_str(self)
+    }
+
+    fn status(&self) -> Source {
+        Line::status(self)
+    }
+}
+
+impl<const L: usize, const C: usize> RingSource for RingLine<L, C> {
+    type Line = Line<C>;
+    type Iter<'a> = LineIter<'a, L, Line<C>>;
+
+    fn iter_history(&self) -> Self::Iter<'_> {
+        RingLine::iter_history(self)
+    }
+
+    fn iter_local_editing(&self) -> Self::Iter<'_> {
+        RingLine::iter_local_editing(self)
+    }
+
+    fn iter_remote_editing(&self) -> Self::Iter<'_> {
+        RingLine::iter_remote_editing(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc_impls {
+    use super::{DisplayLine, RingSource};
+    use crate::{LineIter, LineVec, RingLineVec, Source};
+
+    impl DisplayLine for LineVec {
+        fn as_str(&self) -> &str {
+            LineVec::as_str(self)
+        }
+
+        fn status(&self) -> Source {
+            LineVec::status(self)
+        }
+    }
+
+    impl RingSource for RingLineVec {
+        type Line = LineVec;
+        type Iter<'a> = LineIter<'a, 0, LineVec>;
+
+        fn iter_history(&self) -> Self::Iter<'_> {
+            RingLineVec::iter_history(self)
+        }
+
+        fn iter_local_editing(&self) -> Self::Iter<'_> {
+            RingLineVec::iter_local_editing(self)
+        }
+
+        fn iter_remote_editing(&self) -> Self::Iter<'_> {
+            RingLineVec::iter_remote_editing(self)
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! # Heap Storage
+//!
+//! [RingLineVec] and [LineVec] are the `alloc` counterparts of [RingLine] and
+//! [Line], for hosts where the amount of scrollback isn't known until runtime.
+//! The ordering of lines is shared with [RingLine], only the storage differs.
+
+use alloc::vec::Vec;
+
+use crate::bricks::{VecBricks, MAX_LINES};
+use crate::lines::ascii_good;
+#[cfg(doc)]
+use crate::{Line, RingLine};
+use crate::{LineError, LineIter, RingLineError, Source};
+
+/// A single line with a capacity chosen at runtime
+///
+/// This acts like a [Line], and holds the same [valid ascii] characters, but
+/// the whole capacity is allocated up front when the line is created.
+///
+/// [valid ascii]: u8::is_ascii()
+#[derive(Debug, Clone)]
+pub struct LineVec {
+    buf: Vec<u8>,
+    cap: usize,
+    status: Source,
+    seq: u64,
+    timestamp: Option<u64>,
+    continued: bool,
+}
+
+impl LineVec {
+    /// Create a new empty line, that can hold up to `cap` characters
+    pub fn new(cap: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(cap),
+            cap,
+            status: Source::Local,
+            seq: 0,
+            timestamp: None,
+            continued: false,
+        }
+    }
+
+    /// The source of the current line, either Local or Remote
+    pub fn status(&self) -> Source {
+        self.status
+    }
+
+    /// The sequence number given to this line when it entered the history region,
+    /// see [RingLine::iter_history_seq()]
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// The timestamp given to this line when it was submitted, see [Line::timestamp()]
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    /// Is this line a wrapped continuation of the line before it? See
+    /// [Line::is_continuation()]
+    pub fn is_continuation(&self) -> bool {
+        self.continued
+    }
+
+    /// The maximum number of characters this line can hold
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// The currently used number of bytes in this line
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Is the current line empty?
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Is the current line full?
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.cap
+    }
+
+    /// Pop a character from the END of the line (if any)
+    pub fn pop(&mut self) {
+        self.buf.pop();
+    }
+
+    /// Obtain the current line as a `&str`
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf).unwrap_or("")
+    }
+
+    /// Obtain the current line as a `&[u8]`
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Push an ascii character to the end of the line
+    ///
+    /// Returns an error if the provided character would not fit, or if
+    /// the character is invalid ascii or an ascii control character
+    pub fn push(&mut self, ins: u8) -> Result<(), LineError> {
+        if self.is_full() {
+            return Err(LineError::Full);
+        }
+        ascii_good(ins)?;
+        self.buf.push(ins);
+        Ok(())
+    }
+
+    /// Reset the line for reuse in a new region, see [Line]
+    fn reset(&mut self, status: Source, continued: bool) {
+        self.buf.clear();
+        self.status = status;
+        self.timestamp = None;
+        self.continued = continued;
+    }
+}
+
+impl PartialEq for LineVec {
+    fn eq(&self, other: &Self) -> bool {
+        self.status == other.status && self.buf == other.buf
+    }
+}
+
+/// # RingLineVec
+///
+/// A ring of lines whose number of lines, and characters per line, are chosen
+/// at runtime, using the same regions and recycling as a [RingLine].
+///
+/// Only a small subset of the [RingLine] API is provided: appending and popping
+/// characters in either editing region, appending complete local lines,
+/// submitting, and iterating the regions. Lines always wrap at their capacity,
+/// and there is no carriage return, pinning, attributes, timestamps, undo, or
+/// rewrapping.
+///
+/// The iterators handed out are the same [LineIter] as [RingLine] uses, with an
+/// `L` of `0`, over [LineVec]s.
+#[derive(Debug)]
+pub struct RingLineVec {
+    lines: Vec<LineVec>,
+    brick: VecBricks,
+    next_seq: u64,
+}
+
+impl RingLineVec {
+    /// Create an empty RingLineVec with `lines` lines of up to `chars` characters
+    ///
+    /// Panics if `lines` is larger than [u16::MAX].
+    pub fn new(lines: usize, chars: usize) -> Self {
+        assert!(lines <= MAX_LINES, "Too many lines!");
+        Self {
+            lines: (0..lines).map(|_| LineVec::new(chars)).collect(),
+            brick: VecBricks::with_len(lines),
+            next_seq: 0,
+        }
+    }
+
+    /// The total number of lines, see [RingLineVec::new()]
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The maximum number of characters per line, see [RingLineVec::new()]
+    pub fn line_capacity(&self) -> usize {
+        self.lines.first().map(LineVec::capacity).unwrap_or(0)
+    }
+
+    /// Iterates all "historical" lines, NEWEST to OLDEST, see [RingLine::iter_history()]
+    pub fn iter_history(&self) -> LineIter<'_, 0, LineVec> {
+        let Self { lines, brick, .. } = self;
+        brick.iter_history(lines)
+    }
+
+    /// Obtain a single "historical" line by index, where `0` is the NEWEST line
+    ///
+    /// Returns `None` if there are not that many lines of history.
+    pub fn history(&self, idx: usize) -> Option<&LineVec> {
+        self.iter_history().nth(idx)
+    }
+
+    /// Iterates the local editing region, NEWEST to OLDEST, see
+    /// [RingLine::iter_local_editing()]
+    pub fn iter_local_editing(&self) -> LineIter<'_, 0, LineVec> {
+        let Self { lines, brick, .. } = self;
+        brick.iter_local_editable(lines)
+    }
+
+    /// Iterates the remote editing region, NEWEST to OLDEST, see
+    /// [RingLine::iter_remote_editing()]
+    pub fn iter_remote_editing(&self) -> LineIter<'_, 0, LineVec> {
+        let Self { lines, brick, .. } = self;
+        brick.iter_remote_editable(lines)
+    }
+
+    /// Is every region empty?
+    pub fn is_empty(&self) -> bool {
+        self.iter_history().len() == 0
+            && self.iter_local_editing().len() == 0
+            && self.iter_remote_editing().len() == 0
+    }
+
+    /// Moves the local editing region into a user historical region
+    pub fn submit_local_editing(&mut self) {
+        let Self {
+            lines,
+            brick,
+            next_seq,
+        } = self;
+        for line in brick.iter_local_editable_mut(lines).rev() {
+            line.seq = *next_seq;
+            *next_seq += 1;
+        }
+        self.brick.submit_local_editable();
+    }
+
+    /// Moves the remote editing region into a user historical region
+    pub fn submit_remote_editing(&mut self) {
+        let Self {
+            lines,
+            brick,
+            next_seq,
+        } = self;
+        for line in brick.iter_remote_editable_mut(lines).rev() {
+            line.seq = *next_seq;
+            *next_seq += 1;
+        }
+        self.brick.submit_remote_editable();
+    }
+
+    /// Appends a complete line to the local editing region, and then submits it
+    ///
+    /// See [RingLine::append_local_line()]. If any character is invalid, or the
+    /// whole line does not fit in the buffer, the local editing region is cleared
+    /// and nothing is submitted.
+    pub fn append_local_line(&mut self, s: &str) -> Result<(), RingLineError> {
+        let res = s
+            .bytes()
+            .try_for_each(|c| ascii_good(c).map_err(RingLineError::Line));
+        let res = res.and_then(|()| {
+            let needed = (self.local_editing_len() + s.len())
+                .max(1)
+                .div_ceil(self.line_capacity().max(1));
+            let available = self.line_count() - self.iter_remote_editing().count();
+            if needed > available {
+                Err(RingLineError::Line(LineError::Full))
+            } else {
+                Ok(())
+            }
+        });
+        let res = res.and_then(|()| s.bytes().try_for_each(|c| self.append_local_char(c)));
+
+        match res {
+            Ok(()) => {
+                self.submit_local_editing();
+                Ok(())
+            }
+            Err(e) => {
+                while self.brick.local_editable_front().is_some() {
+                    self.brick.pop_local_editable_front();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Attempts to append a character to the local editing region
+    ///
+    /// Does NOT accept control characters, such as `\n`.
+    pub fn append_local_char(&mut self, c: u8) -> Result<(), RingLineError> {
+        self.get_first_writeable(Source::Local)?.push(c)?;
+        Ok(())
+    }
+
+    /// Attempts to append a character to the remote editing region
+    ///
+    /// Does NOT accept control characters, such as `\n`.
+    pub fn append_remote_char(&mut self, c: u8) -> Result<(), RingLineError> {
+        self.get_first_writeable(Source::Remote)?.push(c)?;
+        Ok(())
+    }
+
+    /// Attempts to remove a character from the local editing region
+    pub fn pop_local_char(&mut self) {
+        let Self { lines, brick, .. } = self;
+        if let Some(cur) = brick.iter_local_editable_mut(lines).next() {
+            if cur.is_empty() {
+                brick.pop_local_editable_front();
+            } else {
+                cur.pop();
+            }
+        }
+    }
+
+    /// Attempts to remove a character from the remote editing region
+    pub fn pop_remote_char(&mut self) {
+        let Self { lines, brick, .. } = self;
+        if let Some(cur) = brick.iter_remote_editable_mut(lines).next() {
+            if cur.is_empty() {
+                brick.pop_remote_editable_front();
+            } else {
+                cur.pop();
+            }
+        }
+    }
+
+    fn local_editing_len(&self) -> usize {
+        self.iter_local_editing().map(LineVec::len).sum()
+    }
+
+    fn get_first_writeable(&mut self, source: Source) -> Result<&mut LineVec, RingLineError> {
+        let Self { lines, brick, .. } = self;
+        let front = match source {
+            Source::Remote => brick.remote_editable_front(),
+            _ => brick.local_editable_front(),
+        };
+        let (wr, new, wrapped) = match front {
+            Some(wr) if !lines[wr].is_full() => (wr, false, false),
+            Some(_) => (Self::insert_front(brick, source)?, true, true),
+            None => (Self::insert_front(brick, source)?, true, false),
+        };
+        let cur = &mut lines[wr];
+        if new {
+            cur.reset(source, wrapped);
+        }
+        Ok(cur)
+    }
+
+    fn insert_front(brick: &mut VecBricks, source: Source) -> Result<usize, RingLineError> {
+        let idx = match source {
+            Source::Remote => brick.insert_remote_editable_front(|_| false),
+            _ => brick.insert_local_editable_front(|_| false),
+        }?;
+        Ok(idx)
+    }
+}
+
+#[cfg(test)]
+mod vec_tests {
+    use super::RingLineVec;
+    use crate::{LineError, RingLineError, Source};
+
+    #[test]
+    fn runtime_sizes() {
+        let mut rline = RingLineVec::new(3, 4);
+        assert_eq!(rline.line_count(), 3);
+        assert_eq!(rline.line_capacity(), 4);
+        assert!(rline.is_empty());
+
+        "hello"
+            .bytes()
+            .for_each(|c| rline.append_local_char(c).unwrap());
+        let editing: Vec<_> = rline.iter_local_editing().map(|l| l.as_str()).collect();
+        assert_eq!(editing, ["o", "hell"]);
+        assert!(rline.iter_local_editing().next().unwrap().is_continuation());
+
+        rline.submit_local_editing();
+        "ok".bytes()
+            .for_each(|c| rline.append_remote_char(c).unwrap());
+        rline.submit_remote_editing();
+        let history: Vec<_> = rline
+            .iter_history()
+            .map(|l| (l.status(), l.seq(), l.as_str()))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (Source::Remote, 2, "ok"),
+                (Source::Local, 1, "o"),
+                (Source::Local, 0, "hell"),
+            ]
+        );
+
+        // The OLDEST history is recycled once the lines run out
+        rline.append_local_line("abc").unwrap();
+        assert_eq!(rline.history(0).unwrap().as_str(), "abc");
+        assert_eq!(rline.iter_history().count(), 3);
+        assert_eq!(rline.history(2).unwrap().as_str(), "o");
+    }
+
+    #[test]
+    fn pop_and_errors() {
+        let mut rline = RingLineVec::new(2, 2);
+        "abc"
+            .bytes()
+            .for_each(|c| rline.append_remote_char(c).unwrap());
+        rline.pop_remote_char();
+        rline.pop_remote_char();
+        let remote: Vec<_> = rline.iter_remote_editing().map(|l| l.as_str()).collect();
+        assert_eq!(remote, ["ab"]);
+
+        // Every line is taken by the remote end, so there's nothing left to type in
+        assert_eq!(
+            rline.append_local_line("xyz"),
+            Err(RingLineError::Line(LineError::Full))
+        );
+        assert_eq!(rline.iter_local_editing().count(), 0);
+        assert_eq!(
+            rline.append_local_line("a\nb"),
+            Err(RingLineError::Line(LineError::InvalidChar))
+        );
+        rline.pop_remote_char();
+        rline.pop_remote_char();
+        rline.pop_remote_char();
+        assert!(rline.is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "embedded-io")]
+
+use embedded_io::{Error, ErrorKind, Write};
+use input_mgr::{AnsiFilter, RingLine, RingLineError};
+
+fn history<const L: usize, const C: usize>(ringline: &RingLine<L, C>) -> Vec<String> {
+    ringline
+        .iter_history()
+        .map(|l| l.as_str().to_string())
+        .collect()
+}
+
+#[test]
+fn newlines_submit() {
+    let mut ringline = RingLine::<8, 8>::new();
+    let mut sink = ringline.remote_sink();
+
+    sink.write_all(b"hello\r\nwonderful world\r\npart").unwrap();
+    sink.flush().unwrap();
+
+    assert_eq!(history(&ringline), ["l world", "wonderfu", "hello"]);
+    let editing = ringline
+        .iter_remote_editing()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(editing, ["part"]);
+}
+
+#[test]
+fn stops_when_full() {
+    let mut ringline = RingLine::<1, 4>::new();
+    let mut sink = ringline.remote_sink();
+
+    assert_eq!(sink.write(b"abcdef"), Ok(4));
+    let err = sink.write(b"ef").unwrap_err();
+    assert_eq!(err, RingLineError::BufferExhausted);
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(sink.write(b""), Ok(0));
+}
+
+#[test]
+fn drops_invalid_bytes() {
+    let mut ringline = RingLine::<8, 8>::new();
+    let mut sink = ringline.remote_sink();
+
+    sink.write_all(b"a\tb\n").unwrap();
+    sink.write_all(b"\x07c\x7fd\n").unwrap();
+    assert_eq!(sink.dropped(), 3);
+
+    assert_eq!(history(&ringline), ["cd", "ab"]);
+}
+
+#[test]
+fn strip_ansi() {
+    let mut ringline = RingLine::<8, 16>::new();
+    let mut filter = AnsiFilter::new();
+
+    ringline
+        .remote_sink()
+        .strip_ansi(&mut filter)
+        .write_all(b"\x1b[32mok\x1b[0m\r\n\x1b[1;")
+        .unwrap();
+    ringline
+        .remote_sink()
+        .strip_ansi(&mut filter)
+        .write_all(b"31mfail\x1b[0m\r\n")
+        .unwrap();
+
+    assert_eq!(history(&ringline), ["fail", "ok"]);
+}
+
+#[test]
+fn backspace_and_carriage_return() {
+    let mut ringline = RingLine::<8, 16>::new();
+    let mut sink = ringline.remote_sink();
+
+    sink.write_all(b"abc\x08\x08X\n").unwrap();
+    sink.write_all(b"hello\rworld\n").unwrap();
+    sink.write_all(b"progress: 10%\rprogress: 100%\r\n")
+        .unwrap();
+    sink.write_all(b"abcdef\rxy").unwrap();
+
+    assert_eq!(history(&ringline), ["progress: 100%", "world", "aX"]);
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "xycdef");
+}
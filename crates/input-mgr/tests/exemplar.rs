@@ -1,5 +1,8 @@
 use core::fmt::Write;
-use input_mgr::{RingLine, Source, LineError};
+use input_mgr::{
+    CommandBuf, LineError, OnInvalid, Region, RegionLine, RingLine, RingLineError, SnapshotBuf,
+    Source, WrapStrategy,
+};
 use textwrap::dedent;
 
 #[test]
@@ -801,6 +804,1833 @@ fn overwrite() {
     );
 }
 
+#[test]
+fn iter_all() {
+    // Create a ringline buffer with 80 characters per line, and 16 lines
+    let mut ringline = RingLine::<16, 80>::new();
+
+    b"hello from local!".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    ringline.submit_local_editing();
+    b"hello from remote!".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    ringline.submit_remote_editing();
+    b"typing remote".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    b"typing local".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+
+    let mut out = String::new();
+    for item in ringline.iter_all().rev() {
+        let tag = match item {
+            RegionLine::History(l) if l.status() == Source::Local => "L.",
+            RegionLine::History(_) => "R.",
+            RegionLine::RemoteEditing(_) => "R#",
+            RegionLine::LocalEditing(_) => "L#",
+        };
+        writeln!(&mut out, "{tag} | {} |", item.line().as_str()).unwrap();
+    }
+    assert_eq!(
+        out.trim(),
+        dedent(
+            r#"
+            L. | hello from local! |
+            R. | hello from remote! |
+            R# | typing remote |
+            L# | typing local |
+        "#
+        )
+        .trim(),
+    );
+
+    // Forward iteration is bottom to top
+    let first = ringline.iter_all().next().unwrap();
+    assert!(matches!(first, RegionLine::LocalEditing(_)));
+    assert_eq!(ringline.iter_all().count(), 4);
+
+    // The number of lines is known up front, without consuming anything
+    let mut all = ringline.iter_all();
+    assert_eq!(all.len(), 4);
+    all.next();
+    all.next_back();
+    assert_eq!(all.size_hint(), (2, Some(2)));
+    assert_eq!(ringline.iter_history().len(), 2);
+    assert_eq!(ringline.iter_remote_editing().len(), 1);
+    assert_eq!(ringline.iter_local_editing().len(), 1);
+}
+
+#[test]
+fn find_history() {
+    // Create a ringline buffer with 80 characters per line, and 16 lines
+    let mut ringline = RingLine::<16, 80>::new();
+
+    for line in ["git status", "On branch main", "git Commit -m wip", "ls"] {
+        line.as_bytes().iter().for_each(|c| {
+            ringline.append_local_char(*c).unwrap();
+        });
+        ringline.submit_local_editing();
+    }
+
+    // Newest to oldest: "ls", "git Commit -m wip", "On branch main", "git status"
+    assert_eq!(ringline.find_history("git", 0), Some(1));
+    assert_eq!(ringline.find_history("git", 2), Some(3));
+    assert_eq!(ringline.find_history("git", 4), None);
+    assert_eq!(ringline.find_history("git", 100), None);
+    assert_eq!(ringline.find_history("", 0), Some(0));
+    assert_eq!(ringline.find_history("commit", 0), None);
+    assert_eq!(ringline.find_history_ci("commit", 0), Some(1));
+    assert_eq!(ringline.find_history_ci("ON BRANCH", 0), Some(2));
+    assert_eq!(ringline.find_history_ci("", 3), Some(3));
+    assert_eq!(ringline.find_history_ci("nope", 0), None);
+
+    let idx = ringline.find_history("branch", 0).unwrap();
+    assert_eq!(ringline.history(idx).unwrap().as_str(), "On branch main");
+    assert!(ringline.history(4).is_none());
+}
+
+#[test]
+fn system_lines() {
+    // Create a ringline buffer with 16 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 16>::new();
+
+    b"hello from local".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    b"hello".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    ringline.push_system_line("--- connected ---").unwrap();
+    ringline.push_system_line("").unwrap();
+    assert_eq!(
+        ringline.push_system_line("bad\n").unwrap_err(),
+        RingLineError::Line(LineError::InvalidChar)
+    );
+
+    let dump = dump_to_string(&ringline);
+    assert_eq!(
+        dump,
+        dedent(
+            r#"
+            ====
+            S. | --- connected -- |
+            S. | - |
+            S. |  |
+            R# | hello |
+            L# | hello from local |
+            ====
+        "#
+        )
+        .trim(),
+    );
+
+    ringline.submit_remote_editing();
+    ringline.submit_local_editing();
+    assert_eq!(ringline.history(0).unwrap().status(), Source::Local);
+    assert_eq!(ringline.history(1).unwrap().status(), Source::Remote);
+    assert_eq!(ringline.history(2).unwrap().status(), Source::System);
+}
+
+#[test]
+fn system_line_too_long() {
+    let mut ringline = RingLine::<4, 4>::new();
+    ringline.push_system_line("boot").unwrap();
+    ringline.append_local_char(b'x').unwrap();
+
+    // Would need 4 lines, only 3 are not editing
+    assert_eq!(
+        ringline.push_system_line("0123456789ab!"),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(ringline.iter_history().count(), 1);
+    assert_eq!(ringline.history(0).unwrap(), "boot");
+
+    ringline.pin_history(0).unwrap();
+    assert_eq!(
+        ringline.push_system_line("01234567!"),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(ringline.iter_history().count(), 1);
+    ringline.push_system_line("01234567").unwrap();
+    assert_eq!(ringline.iter_history().count(), 3);
+}
+
+#[test]
+fn append_local_line() {
+    // Create a ringline buffer with 16 characters per line, and 4 lines
+    let mut ringline = RingLine::<4, 16>::new();
+
+    ringline.append_local_line("hello").unwrap();
+    ringline
+        .append_local_line("this is longer than sixteen")
+        .unwrap();
+
+    let dump = dump_to_string(&ringline);
+    assert_eq!(
+        dump,
+        dedent(
+            r#"
+            ====
+            L. | hello |
+            L. | this is longer t |
+            L. | han sixteen |
+            ====
+        "#
+        )
+        .trim(),
+    );
+
+    // Invalid characters never reach history, and clear the editing region
+    b"typed".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    assert_eq!(
+        ringline.append_local_line("bad\tline").unwrap_err(),
+        RingLineError::Line(LineError::InvalidChar),
+    );
+    assert_eq!(ringline.local_editing_len(), 0);
+
+    // Neither do lines too long to fit in the whole buffer
+    assert_eq!(
+        ringline.append_local_line(&"x".repeat(65)).unwrap_err(),
+        RingLineError::Line(LineError::Full),
+    );
+    assert_eq!(ringline.local_editing_len(), 0);
+    assert_eq!(ringline.iter_history().count(), 3);
+
+    ringline.append_local_line(&"x".repeat(64)).unwrap();
+    assert_eq!(ringline.iter_history().count(), 4);
+}
+
+#[test]
+fn debug_slots() {
+    // Create a ringline buffer with 16 characters per line, and 6 lines
+    let mut ringline = RingLine::<6, 16>::new();
+
+    let slots = ringline.debug_slots().collect::<Vec<_>>();
+    assert_eq!(slots.len(), 6);
+    assert!(slots.iter().all(|(_, region, _)| *region == Region::Free));
+
+    ringline.append_local_line("history").unwrap();
+    b"remote".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    b"local".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+
+    let slots = ringline
+        .debug_slots()
+        .map(|(idx, region, line)| (idx, region, line.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(slots.len(), 6);
+    assert_eq!(slots[0].1, Region::Local);
+    assert_eq!(slots[0].2, "local");
+    assert_eq!(slots[1].1, Region::Remote);
+    assert_eq!(slots[1].2, "remote");
+    assert_eq!(slots[2].1, Region::History);
+    assert_eq!(slots[2].2, "history");
+    assert!(slots[3..]
+        .iter()
+        .all(|(_, region, _)| *region == Region::Free));
+
+    // Every physical slot shows up exactly once
+    let mut idxs = slots.iter().map(|(idx, _, _)| *idx).collect::<Vec<_>>();
+    idxs.sort();
+    assert_eq!(idxs, [0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn trim_history() {
+    // Create a ringline buffer with 80 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 80>::new();
+
+    for i in 0..6 {
+        ringline.append_local_line(&format!("line {i}")).unwrap();
+    }
+    b"remote".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    b"local".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+
+    // No-op when there's already little enough history
+    ringline.trim_history(10);
+    assert_eq!(ringline.iter_history().count(), 6);
+
+    ringline.trim_history(2);
+    let dump = dump_to_string(&ringline);
+    assert_eq!(
+        dump,
+        dedent(
+            r#"
+            ====
+            L. | line 4 |
+            L. | line 5 |
+            R# | remote |
+            L# | local |
+            ====
+        "#
+        )
+        .trim(),
+    );
+
+    // The freed lines are reused before evicting the remaining history
+    ringline.submit_local_editing();
+    for i in 6..10 {
+        ringline.append_local_line(&format!("line {i}")).unwrap();
+    }
+    let dump = dump_to_string(&ringline);
+    assert_eq!(
+        dump,
+        dedent(
+            r#"
+            ====
+            L. | line 4 |
+            L. | line 5 |
+            L. | local |
+            L. | line 6 |
+            L. | line 7 |
+            L. | line 8 |
+            L. | line 9 |
+            R# | remote |
+            ====
+        "#
+        )
+        .trim(),
+    );
+
+    ringline.trim_history(0);
+    assert_eq!(ringline.iter_history().count(), 0);
+    assert_eq!(ringline.iter_remote_editing().count(), 1);
+}
+
+#[test]
+fn sequence_numbers() {
+    // Create a ringline buffer with 16 characters per line, and 4 lines
+    let mut ringline = RingLine::<4, 16>::new();
+
+    ringline.append_local_line("zero").unwrap();
+    b"one".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    ringline.submit_remote_editing();
+    ringline.push_system_line("two").unwrap();
+
+    // Wrapped input takes one number per line, oldest first
+    ringline
+        .append_local_line("three is longer than sixteen")
+        .unwrap();
+
+    let seqs = ringline
+        .iter_history_seq()
+        .map(|(seq, line)| (seq, line.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        seqs,
+        [
+            (4, "than sixteen"),
+            (3, "three is longer "),
+            (2, "two"),
+            (1, "one"),
+        ]
+    );
+
+    // "zero" has been evicted, and is gone for good
+    assert!(ringline.history_by_seq(0).is_none());
+    assert_eq!(ringline.history_by_seq(2).unwrap().as_str(), "two");
+    assert!(ringline.history_by_seq(5).is_none());
+
+    ringline.append_local_line("five").unwrap();
+    assert!(ringline.history_by_seq(1).is_none());
+    assert_eq!(ringline.history_by_seq(5).unwrap().as_str(), "five");
+}
+
+#[test]
+fn const_new() {
+    static EMPTY: RingLine<4, 8> = RingLine::new();
+
+    assert_eq!(EMPTY.iter_history().count(), 0);
+    assert_eq!(EMPTY.iter_local_editing().count(), 0);
+    assert_eq!(EMPTY.iter_remote_editing().count(), 0);
+}
+
+#[test]
+fn default_is_new() {
+    let mut ringline = RingLine::<4, 8>::default();
+    assert_eq!(ringline, RingLine::<4, 8>::new());
+
+    ringline.append_local_char(b'a').unwrap();
+    assert_ne!(ringline, RingLine::<4, 8>::new());
+
+    #[derive(Default)]
+    struct Terminal {
+        lines: RingLine<4, 8>,
+    }
+    assert_eq!(Terminal::default().lines, RingLine::new());
+}
+
+#[test]
+fn logical_eq() {
+    let mut wrapped = RingLine::<4, 8>::new();
+    for line in ["one", "two", "three", "four", "five", "six"] {
+        wrapped.append_local_line(line).unwrap();
+    }
+    wrapped.append_remote_char(b'<').unwrap();
+
+    // Same visible content, but backed by different slots
+    let mut fresh = RingLine::<4, 8>::new();
+    for line in ["four", "five", "six"] {
+        fresh.append_local_line(line).unwrap();
+    }
+    fresh.append_remote_char(b'<').unwrap();
+    assert_eq!(wrapped, fresh);
+
+    // Same text from a different source is not equal
+    let mut remote = RingLine::<4, 8>::new();
+    for line in ["four", "five", "six"] {
+        line.bytes()
+            .for_each(|c| remote.append_remote_char(c).unwrap());
+        remote.submit_remote_editing();
+    }
+    remote.append_remote_char(b'<').unwrap();
+    assert_ne!(wrapped, remote);
+
+    // Same text in a different region is not equal
+    fresh.submit_remote_editing();
+    assert_ne!(wrapped, fresh);
+}
+
+#[test]
+fn try_append_remote_char() {
+    // Create a ringline buffer with 4 characters per line, and 4 lines
+    let mut ringline = RingLine::<4, 4>::new();
+    assert_eq!(ringline.free_lines(), 4);
+
+    ringline.append_local_line("old").unwrap();
+    ringline.append_local_line("new").unwrap();
+    assert_eq!(ringline.free_lines(), 2);
+
+    // Two free lines can be filled without touching history
+    for c in b"12345678" {
+        ringline.try_append_remote_char(*c).unwrap();
+    }
+    assert_eq!(ringline.free_lines(), 0);
+
+    // The next character needs a new line, which would evict "old"
+    assert_eq!(
+        ringline.try_append_remote_char(b'9'),
+        Err(RingLineError::NoFreeLines)
+    );
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, ["new", "old"]);
+    let remote = ringline
+        .iter_remote_editing()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(remote, ["5678", "1234"]);
+
+    // Room in the current line doesn't need a free one
+    ringline.pop_remote_char();
+    ringline.try_append_remote_char(b'9').unwrap();
+
+    // The evicting variant still works
+    ringline.pop_remote_char();
+    ringline.append_remote_char(b'8').unwrap();
+    ringline.append_remote_char(b'9').unwrap();
+    assert_eq!(ringline.iter_history().count(), 1);
+}
+
+#[test]
+fn recall_history() {
+    // Create a ringline buffer with 8 characters per line, and 4 lines
+    let mut ringline = RingLine::<4, 8>::new();
+
+    ringline.append_local_line("ls -la").unwrap();
+    ringline.push_system_line("ok").unwrap();
+    ringline.append_local_line("pwd").unwrap();
+    "typing"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    // Newest first
+    ringline.recall_history_into_local(0).unwrap();
+    assert_eq!(editing_to_string(&ringline), "pwd");
+    ringline.recall_history_into_local(2).unwrap();
+    assert_eq!(editing_to_string(&ringline), "ls -la");
+
+    // Errors leave the editing region alone
+    assert_eq!(
+        ringline.recall_history_into_local(1),
+        Err(RingLineError::NotLocal)
+    );
+    assert_eq!(
+        ringline.recall_history_into_local(3),
+        Err(RingLineError::NoSuchLine)
+    );
+    assert_eq!(editing_to_string(&ringline), "ls -la");
+
+    // The recalled line is editable, and submits as a new history line
+    ringline.append_local_char(b'h').unwrap();
+    ringline.submit_local_editing();
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, ["ls -lah", "pwd", "ok", "ls -la"]);
+
+    // Recalling the oldest line, when it is also the next one to be recycled
+    ringline.recall_history_into_local(3).unwrap();
+    assert_eq!(editing_to_string(&ringline), "ls -la");
+    assert_eq!(ringline.iter_history().count(), 3);
+}
+
+#[test]
+fn compact() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+    for i in 0..20 {
+        ringline.append_local_line(&format!("l{i}")).unwrap();
+        if i % 3 == 0 {
+            ringline.push_system_line("sys").unwrap();
+        }
+    }
+    "rem"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    "loc"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    let before = dump_to_string(&ringline);
+    let seqs = ringline
+        .iter_history_seq()
+        .map(|(seq, _)| seq)
+        .collect::<Vec<_>>();
+    assert!(ringline
+        .debug_slots()
+        .enumerate()
+        .any(|(pos, (idx, _, _))| pos != idx));
+
+    ringline.compact();
+    assert_eq!(dump_to_string(&ringline), before);
+    assert!(ringline.iter_history_seq().map(|(seq, _)| seq).eq(seqs));
+    assert!(ringline
+        .debug_slots()
+        .enumerate()
+        .all(|(pos, (idx, _, _))| pos == idx));
+
+    // Still usable afterwards
+    ringline.submit_local_editing();
+    assert_eq!(ringline.history(0).unwrap().as_str(), "loc");
+}
+
+#[test]
+fn submit_and_get() {
+    // Create a ringline buffer with 8 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 8>::new();
+    ringline.append_local_line("older").unwrap();
+
+    // Nothing to submit
+    assert_eq!(ringline.submit_local_editing_and_get().count(), 0);
+    assert_eq!(ringline.submit_remote_editing_and_get().count(), 0);
+
+    "ls /home/user"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    let submitted = ringline
+        .submit_local_editing_and_get()
+        .rev()
+        .map(|l| l.as_str())
+        .collect::<String>();
+    assert_eq!(submitted, "ls /home/user");
+
+    "ok".bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    let submitted = ringline
+        .submit_remote_editing_and_get()
+        .map(|l| (l.as_str(), l.status()))
+        .collect::<Vec<_>>();
+    assert_eq!(submitted, [("ok", Source::Remote)]);
+    assert_eq!(ringline.iter_history().count(), 4);
+}
+
+#[test]
+fn submit_nonempty() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+
+    // Nothing typed
+    assert!(!ringline.submit_local_editing_nonempty());
+
+    // Only spaces, even across several lines, is cleared without submitting
+    (0..6).for_each(|_| ringline.append_local_char(b' ').unwrap());
+    assert_eq!(ringline.iter_local_editing().count(), 2);
+    assert!(!ringline.submit_local_editing_nonempty());
+    assert_eq!(ringline.iter_local_editing().count(), 0);
+    assert_eq!(ringline.iter_history().count(), 0);
+
+    // Anything else is submitted as-is
+    " hi "
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    assert!(ringline.submit_local_editing_nonempty());
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, [" hi "]);
+}
+
+#[test]
+fn submit_trimmed() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+
+    "hey      "
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    assert_eq!(ringline.iter_local_editing().count(), 3);
+    ringline.submit_local_editing_trimmed();
+
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, ["hey"]);
+}
+
+#[test]
+fn region_lens() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+    assert_eq!(ringline.history_len(), 0);
+
+    ringline.append_local_line("abcdefg").unwrap();
+    ringline.push_system_line("hi").unwrap();
+    "xyzzy"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    "12".bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+
+    assert_eq!(ringline.history_len(), 9);
+    assert_eq!(ringline.local_editing_len(), 5);
+    assert_eq!(ringline.remote_editing_len(), 2);
+}
+
+#[test]
+fn pop_local_word() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+    // No-op when empty
+    ringline.pop_local_word();
+    assert_eq!(editing_to_string(&ringline), "");
+
+    // Mid-word, crossing a wrapped line
+    "cat fil"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    assert_eq!(ringline.iter_local_editing().count(), 2);
+    ringline.pop_local_word();
+    assert_eq!(editing_to_string(&ringline), "cat ");
+
+    // At a boundary, trailing spaces go along with the word before them
+    "readme  "
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    ringline.pop_local_word();
+    assert_eq!(editing_to_string(&ringline), "cat ");
+
+    // A whole line of one word, crossing several wrapped lines
+    ringline.pop_local_word();
+    assert_eq!(editing_to_string(&ringline), "");
+    "abcdefghij"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    ringline.pop_local_word();
+    assert_eq!(editing_to_string(&ringline), "");
+    ringline.append_local_char(b'x').unwrap();
+    assert_eq!(editing_to_string(&ringline), "x");
+}
+
+#[test]
+fn submit_into() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+    "echo hello"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    let mut command = String::new();
+    ringline.submit_local_editing_into(&mut command).unwrap();
+    assert_eq!(command, "echo hello");
+    assert_eq!(ringline.iter_local_editing().count(), 0);
+    assert_eq!(ringline.iter_history().count(), 3);
+
+    // Nothing is submitted if the output can't take it
+    struct Refuse;
+    impl core::fmt::Write for Refuse {
+        fn write_str(&mut self, _: &str) -> core::fmt::Result {
+            Err(core::fmt::Error)
+        }
+    }
+    ringline.append_local_char(b'x').unwrap();
+    assert!(ringline.submit_local_editing_into(&mut Refuse).is_err());
+    assert_eq!(editing_to_string(&ringline), "x");
+}
+
+#[test]
+fn used_lines() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+    assert!(ringline.is_empty());
+    assert_eq!(ringline.used_lines(), 0);
+
+    ringline.append_remote_char(b'a').unwrap();
+    assert!(!ringline.is_empty());
+    assert_eq!(ringline.used_lines(), 1);
+
+    ringline.submit_remote_editing();
+    ringline.append_local_line("hello").unwrap();
+    assert_eq!(ringline.used_lines(), 3);
+    assert_eq!(ringline.used_lines() + ringline.free_lines(), 8);
+
+    ringline.trim_history(0);
+    assert!(ringline.is_empty());
+}
+
+#[test]
+fn soft_line_limit() {
+    // Create a ringline buffer with 16 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 16>::new();
+    assert_eq!(ringline.soft_line_limit(), 16);
+
+    ringline.set_soft_line_limit(4);
+    assert_eq!(ringline.soft_line_limit(), 4);
+    "abcdefghij"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    "xyzzy"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    ringline.push_system_line("123456").unwrap();
+
+    let local = ringline
+        .iter_local_editing()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(local, ["ij", "efgh", "abcd"]);
+    let remote = ringline
+        .iter_remote_editing()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(remote, ["y", "xyzz"]);
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, ["56", "1234"]);
+
+    // Clamped to `C`
+    ringline.set_soft_line_limit(200);
+    assert_eq!(ringline.soft_line_limit(), 16);
+    ringline.set_soft_line_limit(0);
+    assert_eq!(ringline.soft_line_limit(), 1);
+}
+
+#[test]
+fn wide_soft_line_limit() {
+    let mut ringline = RingLine::<4, 320>::new();
+    ringline.set_soft_line_limit(300);
+    assert_eq!(ringline.soft_line_limit(), 300);
+
+    let long = "x".repeat(301);
+    ringline.append_local_line(&long).unwrap();
+    assert_eq!(ringline.history(1).unwrap().len(), 300);
+    assert_eq!(ringline.history(0).unwrap().len(), 1);
+
+    ringline.rewrap(310).unwrap();
+    assert_eq!(ringline.history(0).unwrap().len(), 301);
+}
+
+#[test]
+fn history_message() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+
+    ringline.append_local_line("one").unwrap();
+    ringline.append_local_line("a wrapped").unwrap();
+    "abcd"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    ringline.submit_remote_editing();
+    ringline.push_system_line("sys msg").unwrap();
+
+    let message = |rl: &RingLine<8, 4>, idx: usize| {
+        let mut out = String::new();
+        rl.history_message(idx, &mut out).unwrap();
+        out
+    };
+
+    // Newest first: "sys msg" (2), "abcd" (1), "a wrapped" (3), "one" (1)
+    assert_eq!(message(&ringline, 0), "sys msg");
+    assert_eq!(message(&ringline, 1), "sys msg");
+    assert_eq!(message(&ringline, 2), "abcd");
+    assert_eq!(message(&ringline, 3), "a wrapped");
+    assert_eq!(message(&ringline, 4), "a wrapped");
+    assert_eq!(message(&ringline, 5), "a wrapped");
+    assert_eq!(message(&ringline, 6), "one");
+    assert_eq!(message(&ringline, 7), "");
+
+    // Once the start of a message is recycled, the rest is still available
+    ringline.append_local_line("x").unwrap();
+    ringline.append_local_line("y").unwrap();
+    ringline.append_local_line("z").unwrap();
+    assert_eq!(message(&ringline, 7), "apped");
+    assert_eq!(message(&ringline, 6), "apped");
+}
+
+#[test]
+fn continuations() {
+    // Create a ringline buffer with 4 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 4>::new();
+
+    ringline.append_local_line("abcdefghi").unwrap();
+    ringline.append_local_line("wxyz").unwrap();
+    "remote"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+
+    let flags = ringline
+        .iter_all()
+        .map(|l| (l.line().as_str(), l.line().is_continuation()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        flags,
+        [
+            ("te", true),
+            ("remo", false),
+            ("wxyz", false),
+            ("i", true),
+            ("efgh", true),
+            ("abcd", false),
+        ]
+    );
+
+    // Same text, split differently, is a different conversation
+    let mut wrapped = RingLine::<8, 4>::new();
+    wrapped.append_local_line("abcdefgh").unwrap();
+    let mut split = RingLine::<8, 4>::new();
+    split.append_local_line("abcd").unwrap();
+    split.append_local_line("efgh").unwrap();
+    assert_ne!(wrapped, split);
+}
+
+#[test]
+fn rewrap() {
+    // Create a ringline buffer with 8 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 8>::new();
+    ringline.append_local_line("hello world").unwrap();
+    "ok".bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    ringline.submit_remote_editing_at(42);
+    "typing"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    let original = dump_to_string(&ringline);
+
+    ringline.rewrap(4).unwrap();
+    assert_eq!(ringline.soft_line_limit(), 4);
+    let history = ringline
+        .iter_history()
+        .map(|l| (l.as_str(), l.status(), l.timestamp()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        history,
+        [
+            ("ok", Source::Remote, Some(42)),
+            ("rld", Source::Local, None),
+            ("o wo", Source::Local, None),
+            ("hell", Source::Local, None),
+        ]
+    );
+    assert_eq!(editing_to_string(&ringline), "typing");
+    assert_eq!(ringline.iter_local_editing().count(), 2);
+
+    let mut message = String::new();
+    ringline.history_message(2, &mut message).unwrap();
+    assert_eq!(message, "hello world");
+
+    // Going back to the original width restores the original layout
+    ringline.rewrap(8).unwrap();
+    assert_eq!(dump_to_string(&ringline), original);
+
+    // Narrower still, the oldest history no longer fits
+    ringline.rewrap(2).unwrap();
+    let history = ringline
+        .iter_history()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(history, ["ok", "d", "rl", "wo", "o "]);
+    assert_eq!(editing_to_string(&ringline), "typing");
+}
+
+#[test]
+fn rewrap_in_place() {
+    let mut ringline = RingLine::<8, 8>::new();
+    ringline.append_local_line("hello world").unwrap();
+    ringline.append_local_line("pinned").unwrap();
+    let pin = ringline.pin_history(0).unwrap();
+    ringline.set_local_prompt("> ").unwrap();
+    "abc"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    let history_seq = |ringline: &RingLine<8, 8>| {
+        ringline
+            .iter_history_seq()
+            .map(|(seq, l)| (seq, l.as_str().to_string()))
+            .collect::<Vec<_>>()
+    };
+    let original = history_seq(&ringline);
+
+    let mut snap = SnapshotBuf::new();
+    ringline.snapshot(&mut snap);
+
+    // Lines keep their sequence numbers, lines split off get new ones, and
+    // pinned lines are left as they are
+    ringline.rewrap(4).unwrap();
+    assert_eq!(
+        history_seq(&ringline),
+        [
+            (2, "pinned".to_string()),
+            (1, "rld".to_string()),
+            (3, "o wo".to_string()),
+            (0, "hell".to_string()),
+        ]
+    );
+    assert!(ringline.history(0).unwrap().is_pinned());
+    let diff = ringline.diff(&snap);
+    assert_eq!((diff.added(), diff.evicted()), (1, 0));
+    assert!(diff.is_modified(3));
+
+    // So each line can still be pinned and released on its own
+    let hell = ringline.pin_history(3).unwrap();
+    assert_eq!(hell.seq(), 0);
+    ringline.unpin(hell).unwrap();
+    assert!(ringline.iter_history().skip(1).all(|l| !l.is_pinned()));
+
+    // The prompt is kept
+    assert_eq!(editing_to_string(&ringline), "> abc");
+    assert_eq!(ringline.iter_local_editing().count(), 2);
+    for _ in 0..4 {
+        ringline.pop_local_char();
+    }
+    assert_eq!(editing_to_string(&ringline), "> ");
+
+    ringline.rewrap(8).unwrap();
+    assert_eq!(history_seq(&ringline), original);
+    assert_eq!(ringline.history_by_seq(1).unwrap(), "rld");
+    ringline.unpin(pin).unwrap();
+}
+
+#[test]
+fn source_u8() {
+    for src in [Source::Local, Source::Remote, Source::System] {
+        assert_eq!(Source::from_u8(src.as_u8()), Some(src));
+        assert_eq!(Source::try_from(u8::from(src)), Ok(src));
+    }
+    assert_eq!(Source::Local.as_u8(), 0);
+    assert_eq!(Source::Remote.as_u8(), 1);
+    assert_eq!(Source::System.as_u8(), 2);
+    for val in 3..=u8::MAX {
+        assert_eq!(Source::from_u8(val), None);
+        assert_eq!(Source::try_from(val), Err(val));
+    }
+}
+
+#[test]
+fn export_bytes() {
+    let mut ringline = RingLine::<8, 8>::new();
+    let export = |rl: &RingLine<8, 8>, prefixed: bool| {
+        let mut bytes = Vec::new();
+        let mut out = |b: &[u8]| bytes.extend_from_slice(b);
+        if prefixed {
+            rl.export_bytes_prefixed(&mut out);
+        } else {
+            rl.export_bytes(&mut out);
+        }
+        String::from_utf8(bytes).unwrap()
+    };
+    assert_eq!(export(&ringline, false), "");
+
+    ringline.append_local_line("hello world").unwrap();
+    "hi!"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    ringline.submit_remote_editing();
+    ringline.push_system_line("bye").unwrap();
+    "typing"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    assert_eq!(export(&ringline, false), "hello world\nhi!\nbye");
+    assert_eq!(export(&ringline, true), "L: hello world\nR: hi!\nS: bye");
+}
+
+#[test]
+fn long_lines() {
+    // Lines longer than 255 characters
+    let mut ringline = RingLine::<4, 320>::new();
+    let long = "0123456789".repeat(32);
+    ringline.append_local_line(&long).unwrap();
+    assert_eq!(ringline.history(0).unwrap().as_str(), long);
+    assert_eq!(ringline.history_len(), 320);
+
+    long.bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    assert_eq!(ringline.iter_local_editing().count(), 1);
+    ringline.append_local_char(b'!').unwrap();
+    assert_eq!(ringline.iter_local_editing().count(), 2);
+    ringline.pop_local_char();
+    ringline.pop_local_char();
+    assert_eq!(ringline.local_editing_len(), 320);
+    assert_eq!(ringline.iter_local_editing().count(), 1);
+    ringline.pop_local_char();
+    assert_eq!(ringline.local_editing_len(), 319);
+}
+
+#[test]
+fn current_lines() {
+    let mut ringline = RingLine::<8, 4>::new();
+    assert!(ringline.current_local_line().is_none());
+    assert!(ringline.current_remote_line().is_none());
+
+    "hello"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+    "hi".bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    assert_eq!(ringline.current_local_line().unwrap().as_str(), "o");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "hi");
+
+    ringline.submit_local_editing();
+    assert!(ringline.current_local_line().is_none());
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "hi");
+}
+
+#[test]
+fn remote_flood_keeps_local() {
+    let mut ringline = RingLine::<4, 4>::new();
+    ringline.append_local_line("old").unwrap();
+    "abcdefg"
+        .bytes()
+        .for_each(|c| ringline.append_local_char(c).unwrap());
+
+    // The remote end takes the free line, then the history line, then gives up
+    let mut sent = 0;
+    for c in b"0123456789".iter().cycle().take(64) {
+        if ringline.append_remote_char(*c).is_err() {
+            break;
+        }
+        sent += 1;
+    }
+    assert_eq!(sent, 8);
+    assert_eq!(ringline.iter_history().count(), 0);
+    assert_eq!(editing_to_string(&ringline), "abcdefg");
+    let remote = ringline
+        .iter_remote_editing()
+        .rev()
+        .map(|l| l.as_str())
+        .collect::<String>();
+    assert_eq!(remote, "01234567");
+
+    // Local typing continues in the room left in its line
+    ringline.append_local_char(b'h').unwrap();
+    assert_eq!(editing_to_string(&ringline), "abcdefgh");
+    ringline.submit_remote_editing();
+    ringline.append_remote_char(b'8').unwrap();
+    assert_eq!(editing_to_string(&ringline), "abcdefgh");
+}
+
+#[test]
+fn set_history_status() {
+    let mut ringline = RingLine::<4, 8>::new();
+    ringline.append_local_line("mine").unwrap();
+    "theirs"
+        .bytes()
+        .for_each(|c| ringline.append_remote_char(c).unwrap());
+    ringline.submit_remote_editing();
+
+    ringline.set_history_status(1, Source::Remote).unwrap();
+    ringline.set_history_status(0, Source::Local).unwrap();
+    let history = ringline
+        .iter_history()
+        .map(|l| (l.as_str(), l.status()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        history,
+        [("theirs", Source::Local), ("mine", Source::Remote)]
+    );
+
+    assert_eq!(
+        ringline.set_history_status(2, Source::System),
+        Err(RingLineError::NoSuchLine)
+    );
+}
+
+#[test]
+fn remote_carriage_return() {
+    let mut ringline = RingLine::<4, 8>::new();
+    let feed = |rl: &mut RingLine<4, 8>, s: &str| {
+        s.bytes().for_each(|c| rl.append_remote_char(c).unwrap());
+    };
+
+    // Overwrites, then appends once past the end
+    feed(&mut ringline, "abc");
+    ringline.remote_carriage_return();
+    feed(&mut ringline, "xy");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "xyc");
+    feed(&mut ringline, "zw");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "xyzw");
+
+    // Popping returns to appending
+    ringline.remote_carriage_return();
+    ringline.pop_remote_char();
+    feed(&mut ringline, "!");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "xyz!");
+
+    // As does submitting, and a return on an empty region does nothing
+    ringline.remote_carriage_return();
+    ringline.submit_remote_editing();
+    feed(&mut ringline, "new");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "new");
+    assert_eq!(ringline.history(0).unwrap().as_str(), "xyz!");
+    ringline.submit_remote_editing();
+    ringline.remote_carriage_return();
+    feed(&mut ringline, "ok");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "ok");
+
+    // A rejected character doesn't end the overwrite
+    ringline.submit_remote_editing();
+    feed(&mut ringline, "hello");
+    ringline.remote_carriage_return();
+    assert_eq!(
+        ringline.append_remote_char(0x07),
+        Err(RingLineError::Line(LineError::InvalidChar))
+    );
+    feed(&mut ringline, "world");
+    assert_eq!(ringline.current_remote_line().unwrap().as_str(), "world");
+}
+
+#[test]
+fn local_line_remaining() {
+    let mut rline = RingLine::<4, 8>::new();
+    assert_eq!(rline.local_line_remaining(), 8);
+    "hello"
+        .bytes()
+        .for_each(|c| rline.append_local_char(c).unwrap());
+    assert_eq!(rline.local_line_remaining(), 3);
+    "abc"
+        .bytes()
+        .for_each(|c| rline.append_local_char(c).unwrap());
+    assert_eq!(rline.local_line_remaining(), 0);
+
+    // The next character wraps onto a new line
+    rline.append_local_char(b'd').unwrap();
+    assert_eq!(rline.local_line_remaining(), 7);
+
+    rline.submit_local_editing();
+    rline.set_soft_line_limit(4);
+    assert_eq!(rline.local_line_remaining(), 4);
+}
+
+#[test]
+fn append_chars() {
+    let mut rline = RingLine::<4, 8>::new();
+    "hi!".chars().for_each(|c| rline.append_local(c).unwrap());
+    "yo".chars().for_each(|c| rline.append_remote(c).unwrap());
+    assert_eq!(
+        rline.append_local('é'),
+        Err(RingLineError::Line(LineError::InvalidChar))
+    );
+    assert_eq!(
+        rline.append_remote('\n'),
+        Err(RingLineError::Line(LineError::InvalidChar))
+    );
+    assert_eq!(editing_to_string(&rline), "hi!");
+    assert_eq!(rline.current_remote_line().unwrap().as_str(), "yo");
+}
+
+#[test]
+fn local_editing_chars() {
+    let mut rline = RingLine::<4, 4>::new();
+    assert_eq!(rline.local_editing_chars().next(), None);
+    "hello world"
+        .bytes()
+        .for_each(|c| rline.append_local_char(c).unwrap());
+    assert_eq!(rline.iter_local_editing().count(), 3);
+
+    let forwards = rline.local_editing_chars().collect::<Vec<_>>();
+    assert_eq!(forwards, b"hello world");
+    let backwards = rline.local_editing_chars().rev().collect::<Vec<_>>();
+    assert_eq!(backwards, b"dlrow olleh");
+}
+
+#[test]
+fn pin_history() {
+    let mut rline = RingLine::<4, 8>::new();
+    for line in ["a", "b", "c", "d"] {
+        rline.append_local_line(line).unwrap();
+    }
+    let history = |rline: &RingLine<4, 8>| {
+        rline
+            .iter_history()
+            .map(|l| l.as_str().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // The OLDEST line is pinned, so the next OLDEST is recycled instead
+    let pin_a = rline.pin_history(3).unwrap();
+    assert!(rline.history(3).unwrap().is_pinned());
+    rline.append_local_line("e").unwrap();
+    assert_eq!(history(&rline), ["e", "d", "c", "a"]);
+    assert_eq!(rline.history_by_seq(pin_a.seq()).unwrap().as_str(), "a");
+
+    // With every line pinned, there's no room left to type
+    let pins = [0, 1, 2].map(|idx| rline.pin_history(idx).unwrap());
+    assert_eq!(rline.append_local_char(b'x'), Err(RingLineError::AllPinned));
+    assert_eq!(
+        rline.append_local_line("x"),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(rline.pin_history(4), Err(RingLineError::NoSuchLine));
+
+    // Unpinning makes lines available again
+    rline.unpin(pin_a).unwrap();
+    rline.append_local_line("f").unwrap();
+    assert_eq!(history(&rline), ["f", "e", "d", "c"]);
+    assert_eq!(rline.unpin(pin_a), Err(RingLineError::NoSuchLine));
+
+    // Trimming keeps pinned lines too
+    rline.unpin(pins[2]).unwrap();
+    rline.trim_history(1);
+    assert_eq!(history(&rline), ["f", "e", "d"]);
+}
+
+#[test]
+fn read_only_editing_line() {
+    let mut rline = RingLine::<4, 8>::new();
+    "$ ".bytes()
+        .for_each(|c| rline.append_local_char(c).unwrap());
+    rline
+        .iter_local_editing_mut()
+        .next()
+        .unwrap()
+        .set_read_only(true);
+
+    // The line can't be typed in or erased
+    assert_eq!(
+        rline.append_local_char(b'x'),
+        Err(RingLineError::Line(LineError::ReadOnly))
+    );
+    rline.pop_local_char();
+    rline.pop_local_word();
+    assert_eq!(editing_to_string(&rline), "$ ");
+
+    // Once submitted and recycled, the line is writable again
+    rline.submit_local_editing();
+    assert!(rline.history(0).unwrap().is_read_only());
+    for line in ["a", "b", "c", "d"] {
+        rline.append_local_line(line).unwrap();
+    }
+    assert!(rline.iter_history().all(|l| !l.is_read_only()));
+}
+
+#[test]
+fn local_prompt() {
+    let mut rline = RingLine::<4, 8>::new();
+    rline.set_local_prompt("$ ").unwrap();
+    "ls -la"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert_eq!(editing_to_string(&rline), "$ ls -la");
+
+    rline.pop_local_word();
+    rline.pop_local_word();
+    rline.pop_local_word();
+    assert_eq!(editing_to_string(&rline), "$ ");
+    rline.pop_local_char();
+    assert_eq!(editing_to_string(&rline), "$ ");
+
+    "ls".bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    let cur = rline.current_local_line().unwrap();
+    assert_eq!(cur.editable_str(), "ls");
+
+    rline.submit_local_editing();
+    assert_eq!(rline.history(0).unwrap().as_str(), "$ ls");
+    assert_eq!(rline.iter_local_editing().count(), 0);
+}
+
+#[test]
+fn local_prompt_submit_and_recall() {
+    let mut rline = RingLine::<4, 8>::new();
+    rline.set_local_prompt("$ ").unwrap();
+
+    // Only the prompt is blank, and the prompt stays
+    assert!(!rline.submit_local_editing_nonempty());
+    assert_eq!(rline.iter_history().count(), 0);
+    assert_eq!(editing_to_string(&rline), "$ ");
+
+    "ls".bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert!(rline.submit_local_editing_nonempty());
+    assert_eq!(rline.history(0).unwrap().as_str(), "$ ls");
+
+    // Recalling keeps the current prompt, and the recalled one is not copied
+    rline.set_local_prompt("% ").unwrap();
+    rline.recall_history_into_local(0).unwrap();
+    assert_eq!(editing_to_string(&rline), "% ls");
+    rline.pop_local_word();
+    rline.pop_local_word();
+    assert_eq!(editing_to_string(&rline), "% ");
+}
+
+#[test]
+fn replace_local_editing() {
+    let mut rline = RingLine::<4, 8>::new();
+    "git ch"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    rline.replace_local_editing("git checkout").unwrap();
+    assert_eq!(editing_to_string(&rline), "git checkout");
+    assert_eq!(rline.iter_local_editing().count(), 2);
+
+    // Invalid or oversized replacements leave the region as it was
+    assert_eq!(
+        rline.replace_local_editing("a\nb"),
+        Err(RingLineError::Line(LineError::InvalidChar))
+    );
+    assert_eq!(
+        rline.replace_local_editing(&"x".repeat(33)),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(editing_to_string(&rline), "git checkout");
+
+    // The prompt is kept
+    rline.replace_local_editing("").unwrap();
+    assert_eq!(rline.iter_local_editing().count(), 0);
+    rline.set_local_prompt("$ ").unwrap();
+    "ls".bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    rline.replace_local_editing("cargo test").unwrap();
+    assert_eq!(editing_to_string(&rline), "$ cargo test");
+    rline.pop_local_word();
+    rline.pop_local_word();
+    assert_eq!(editing_to_string(&rline), "$ ");
+}
+
+#[test]
+fn on_evict() {
+    use std::sync::Mutex;
+
+    static EVICTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    fn persist(line: &input_mgr::Line<8>) {
+        EVICTED.lock().unwrap().push(line.as_str().to_string());
+    }
+
+    let mut rline = RingLine::<3, 8>::new();
+    rline.on_evict(Some(persist));
+    for line in ["one", "two", "three"] {
+        rline.append_local_line(line).unwrap();
+    }
+    assert!(EVICTED.lock().unwrap().is_empty());
+
+    // Typing takes the oldest line, which is handed over before it is cleared
+    rline.append_local_char(b'x').unwrap();
+    rline.append_remote_char(b'y').unwrap();
+    assert_eq!(*EVICTED.lock().unwrap(), ["one", "two"]);
+
+    rline.submit_local_editing();
+    rline.trim_history(1);
+    assert_eq!(*EVICTED.lock().unwrap(), ["one", "two", "three"]);
+
+    rline.on_evict(None);
+    rline.append_local_line("four").unwrap();
+    rline.append_local_line("five").unwrap();
+    assert_eq!(EVICTED.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn load_history() {
+    let transcript = [
+        (Source::Remote, "login:"),
+        (Source::Local, "r\u{f8}\to\u{f8}t"),
+        (Source::System, "welcome back!"),
+    ];
+
+    let mut rline = RingLine::<4, 8>::new();
+    rline.append_local_char(b'x').unwrap();
+    assert_eq!(rline.load_history(transcript, OnInvalid::Skip), Ok(2));
+    let history = rline
+        .iter_history()
+        .rev()
+        .map(|l| (l.status(), l.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        history,
+        [
+            (Source::Remote, "login:"),
+            (Source::System, "welcome "),
+            (Source::System, "back!"),
+        ]
+    );
+    assert!(rline.history(0).unwrap().is_continuation());
+    assert_eq!(editing_to_string(&rline), "x");
+
+    // The oldest history makes room for the rest
+    let mut rline = RingLine::<3, 8>::new();
+    let loaded = rline.load_history(transcript, OnInvalid::Replace(b'o'));
+    assert_eq!(loaded, Ok(3));
+    let history = rline.iter_history().rev().map(|l| l.as_str());
+    assert_eq!(history.collect::<Vec<_>>(), ["roooot", "welcome ", "back!"]);
+}
+
+#[test]
+fn clear_all() {
+    let mut rline = RingLine::<4, 8>::new();
+    rline.set_soft_line_limit(4);
+    rline.append_local_line("one").unwrap();
+    rline.append_remote_char(b'r').unwrap();
+    rline.set_local_prompt("$ ").unwrap();
+    rline.pin_history(0).unwrap();
+    let seq = rline.iter_history_seq().next().unwrap().0;
+
+    rline.clear_all();
+    assert!(rline.is_empty());
+    assert_eq!(rline.free_lines(), 4);
+    assert_eq!(rline.soft_line_limit(), 4);
+
+    // Every line is usable again, and sequence numbers keep counting
+    for line in ["a", "b", "c", "d"] {
+        rline.append_local_line(line).unwrap();
+    }
+    assert_eq!(rline.history_len(), 4);
+    assert!(rline.iter_history().all(|l| !l.is_pinned()));
+    assert!(rline.iter_history_seq().all(|(s, _)| s > seq));
+}
+
+#[test]
+fn buffer_exhausted() {
+    let mut rline = RingLine::<2, 4>::new();
+    "abcdefgh"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert_eq!(
+        rline.append_local_char(b'i'),
+        Err(RingLineError::BufferExhausted)
+    );
+    assert_eq!(
+        rline.append_remote_char(b'r'),
+        Err(RingLineError::BufferExhausted)
+    );
+
+    // A full line on its own still wraps
+    rline.submit_local_editing();
+    "abcde"
+        .bytes()
+        .for_each(|b| rline.append_remote_char(b).unwrap());
+    assert_eq!(rline.iter_remote_editing().count(), 2);
+}
+
+#[test]
+fn iter_history_range() {
+    let mut rline = RingLine::<8, 8>::new();
+    for line in ["a", "b", "c", "d", "e"] {
+        rline.append_local_line(line).unwrap();
+    }
+    rline.append_remote_char(b'r').unwrap();
+
+    let range = |start, count| {
+        rline
+            .iter_history_range(start, count)
+            .map(|l| l.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(range(0, 2), ["e", "d"]);
+    assert_eq!(range(1, 3), ["d", "c", "b"]);
+    assert_eq!(range(3, 10), ["b", "a"]);
+    assert!(range(5, 1).is_empty());
+    assert!(range(usize::MAX, usize::MAX).is_empty());
+    let oldest = rline.iter_history_range(1, 3).next_back().unwrap();
+    assert_eq!(oldest.as_str(), "b");
+}
+
+#[test]
+fn submit_local_editing_command() {
+    let mut rline = RingLine::<4, 8>::new();
+    let mut cmd = CommandBuf::<32>::new();
+    rline.set_local_prompt("> ").unwrap();
+    "echo hello world"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    rline.submit_local_editing_command(&mut cmd).unwrap();
+    assert_eq!(cmd.split(), ("echo", "hello world"));
+    assert_eq!(cmd.as_str(), "echo hello world");
+    assert_eq!(rline.iter_local_editing().count(), 0);
+    assert_eq!(rline.history(2).unwrap().as_str(), "> echo h");
+
+    "quit"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    rline.submit_local_editing_command(&mut cmd).unwrap();
+    assert_eq!(cmd.cmd(), "quit");
+    assert_eq!(cmd.args(), "");
+
+    // Too long for the buffer, so nothing is submitted
+    let mut small = CommandBuf::<4>::new();
+    "ls -la"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert_eq!(
+        rline.submit_local_editing_command(&mut small),
+        Err(LineError::Full)
+    );
+    assert!(small.is_empty());
+    assert_eq!(editing_to_string(&rline), "ls -la");
+}
+
+#[test]
+fn wrap_strategy() {
+    let mut rline = RingLine::<4, 4>::new();
+    assert_eq!(rline.wrap_strategy(), WrapStrategy::Wrap);
+
+    rline.set_wrap_strategy(WrapStrategy::Truncate);
+    "abcdef"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert_eq!(editing_to_string(&rline), "abcd");
+    "rem"
+        .bytes()
+        .for_each(|b| rline.append_remote_char(b).unwrap());
+    rline.append_remote('.').unwrap();
+    rline.append_remote('!').unwrap();
+    assert_eq!(rline.current_remote_line().unwrap().as_str(), "rem.");
+
+    rline.set_wrap_strategy(WrapStrategy::Reject);
+    assert_eq!(
+        rline.append_local_char(b'e'),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(
+        rline.replace_local_editing("abcde"),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    rline.replace_local_editing("wxyz").unwrap();
+    assert_eq!(rline.iter_local_editing().count(), 1);
+
+    // Back to wrapping, a new line is started
+    rline.set_wrap_strategy(WrapStrategy::Wrap);
+    rline.append_local_char(b'e').unwrap();
+    assert_eq!(editing_to_string(&rline), "wxyze");
+    assert_eq!(rline.iter_local_editing().count(), 2);
+}
+
+#[test]
+fn split_local_at() {
+    let mut rline = RingLine::<4, 16>::new();
+    rline.split_local_at(0).unwrap();
+    assert_eq!(rline.iter_local_editing().count(), 0);
+
+    "hello world"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    rline.split_local_at(6).unwrap();
+    rline.append_local_char(b'!').unwrap();
+    let lines = rline
+        .iter_local_editing()
+        .rev()
+        .map(|l| l.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, ["hello ", "world!"]);
+    assert!(!rline.current_local_line().unwrap().is_continuation());
+
+    // Past the end of the line, nothing changes
+    assert_eq!(
+        rline.split_local_at(7),
+        Err(RingLineError::Line(LineError::WriteGap))
+    );
+    assert_eq!(rline.iter_local_editing().count(), 2);
+
+    // Each line is submitted as its own message
+    rline.submit_local_editing();
+    let mut out = String::new();
+    rline.history_message(0, &mut out).unwrap();
+    assert_eq!(out, "world!");
+}
+
+#[cfg(feature = "undo")]
+#[test]
+fn undo_local() {
+    let mut rline = RingLine::<4, 4>::new();
+    assert!(!rline.undo_local());
+
+    "hello"
+        .bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+    assert_eq!(rline.iter_local_editing().count(), 2);
+    assert!(rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "hell");
+    assert_eq!(rline.iter_local_editing().count(), 1);
+
+    // Only one level
+    assert!(!rline.undo_local());
+
+    rline.pop_local_word();
+    assert_eq!(editing_to_string(&rline), "");
+    assert!(rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "hell");
+
+    rline.replace_local_editing("world!").unwrap();
+    assert!(rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "hell");
+
+    // Submitting discards the undo
+    rline.pop_local_char();
+    rline.submit_local_editing();
+    assert!(!rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "");
+    assert_eq!(rline.history(0).unwrap(), "hel");
+}
+
+#[cfg(feature = "undo")]
+#[test]
+fn undo_after_rejected_edits() {
+    let mut rline = RingLine::<4, 4>::new();
+    "ab".bytes()
+        .for_each(|b| rline.append_local_char(b).unwrap());
+
+    // A rejected append keeps the previous undo
+    assert!(rline.append_local_char(b'\n').is_err());
+    assert_eq!(editing_to_string(&rline), "ab");
+    assert!(rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "a");
+
+    // So does a pop that changes nothing: the last pop removes the empty line
+    rline.pop_local_char();
+    rline.pop_local_char();
+    assert_eq!(rline.iter_local_editing().count(), 0);
+    rline.pop_local_char();
+    rline.pop_local_word();
+    assert!(rline.undo_local());
+    assert_eq!(rline.iter_local_editing().count(), 1);
+    assert_eq!(editing_to_string(&rline), "");
+
+    // And backspacing into a prompt
+    let mut rline = RingLine::<4, 4>::new();
+    rline.set_local_prompt("> ").unwrap();
+    rline.append_local_char(b'x').unwrap();
+    rline.pop_local_char();
+    rline.pop_local_char();
+    rline.pop_local_word();
+    assert!(rline.undo_local());
+    assert_eq!(editing_to_string(&rline), "> x");
+}
+
+#[test]
+fn iter_history_padded() {
+    let mut rline = RingLine::<8, 8>::new();
+    assert!(rline.iter_history_padded(2).all(|l| l.is_none()));
+
+    rline.append_local_line("a").unwrap();
+    rline.append_local_line("b").unwrap();
+    rline.append_local_line("c").unwrap();
+
+    let padded = |rows| {
+        rline
+            .iter_history_padded(rows)
+            .map(|l| l.map(|l| l.as_str()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(padded(5), [None, None, Some("a"), Some("b"), Some("c")]);
+    assert_eq!(padded(2), [Some("b"), Some("c")]);
+    assert!(padded(0).is_empty());
+}
+
+#[cfg(feature = "attrs")]
+#[test]
+fn line_attrs() {
+    let mut rline = RingLine::<8, 4>::new();
+    rline.append_local_line_with_attr("hello", 2).unwrap();
+    assert!(rline.iter_history().all(|l| l.attr() == 2));
+
+    rline.append_remote_char_with_attr(b'x', 5).unwrap();
+    rline.append_local_char_with_attr(b'y', 7).unwrap();
+    assert_eq!(rline.current_remote_line().unwrap().attr(), 5);
+    assert_eq!(rline.current_local_line().unwrap().attr(), 7);
+
+    // Recycled lines start over
+    rline.submit_local_editing();
+    rline.append_local_line("plain").unwrap();
+    assert_eq!(rline.history(0).unwrap().attr(), 0);
+}
+
+#[test]
+fn set_remote_editing_line() {
+    let mut rline = RingLine::<4, 4>::new();
+    "50%"
+        .bytes()
+        .for_each(|b| rline.append_remote_char(b).unwrap());
+    rline.set_remote_editing_line("100%!").unwrap();
+    let remote: Vec<_> = rline
+        .iter_remote_editing()
+        .rev()
+        .map(|l| l.as_str())
+        .collect();
+    assert_eq!(remote, ["100%", "!"]);
+
+    rline.set_remote_editing_line("done").unwrap();
+    assert_eq!(rline.iter_remote_editing().count(), 1);
+    assert_eq!(rline.current_remote_line().unwrap(), "done");
+    assert_eq!(rline.history_len(), 0);
+
+    // Invalid or too long, nothing changes
+    assert_eq!(
+        rline.set_remote_editing_line("a\rb"),
+        Err(RingLineError::Line(LineError::InvalidChar))
+    );
+    assert_eq!(
+        rline.set_remote_editing_line("0123456789abcdefg"),
+        Err(RingLineError::Line(LineError::Full))
+    );
+    assert_eq!(rline.current_remote_line().unwrap(), "done");
+
+    rline.set_remote_editing_line("").unwrap();
+    assert_eq!(rline.iter_remote_editing().count(), 1);
+    assert!(rline.current_remote_line().unwrap().is_empty());
+}
+
+#[test]
+fn iter_history_source() {
+    let mut rline = RingLine::<8, 8>::new();
+    rline.append_local_line("ls").unwrap();
+    "ok".bytes()
+        .for_each(|b| rline.append_remote_char(b).unwrap());
+    rline.submit_remote_editing();
+    rline.push_system_line("boot").unwrap();
+    rline.append_local_line("cd").unwrap();
+
+    let local: Vec<_> = rline
+        .iter_history_source(Source::Local)
+        .map(|l| l.as_str())
+        .collect();
+    assert_eq!(local, ["cd", "ls"]);
+    assert_eq!(rline.iter_history_source(Source::Remote).count(), 1);
+    let system = rline.iter_history_source(Source::System).next_back();
+    assert_eq!(system.unwrap(), "boot");
+}
+
+#[test]
+fn byte_size() {
+    const _: () = assert!(RingLine::<16, 80>::byte_size() < 8 * 1024);
+
+    let size = RingLine::<16, 80>::byte_size();
+    assert_eq!(size, core::mem::size_of::<RingLine<16, 80>>());
+    assert!(size >= 16 * core::mem::size_of::<input_mgr::Line<80>>());
+}
+
+#[test]
+fn drain_oldest_history() {
+    let mut rline = RingLine::<8, 8>::new();
+    rline.append_local_line("one").unwrap();
+    "two"
+        .bytes()
+        .for_each(|b| rline.append_remote_char(b).unwrap());
+    rline.submit_remote_editing();
+    rline.append_local_line("three").unwrap();
+    rline.append_local_line("four").unwrap();
+    rline.append_local_char(b'x').unwrap();
+
+    let mut drained = Vec::new();
+    rline.drain_oldest_history(2, &mut |src, s| drained.push((src, s.to_string())));
+    assert_eq!(
+        drained,
+        [
+            (Source::Local, "one".to_string()),
+            (Source::Remote, "two".to_string())
+        ]
+    );
+    assert_eq!(rline.iter_history().count(), 2);
+    assert_eq!(rline.history(1).unwrap(), "three");
+    assert_eq!(editing_to_string(&rline), "x");
+    assert_eq!(rline.free_lines(), 5);
+
+    // Pinned lines are kept
+    rline.pin_history(1).unwrap();
+    drained.clear();
+    rline.drain_oldest_history(10, &mut |_, s| drained.push((Source::Local, s.to_string())));
+    assert_eq!(drained, [(Source::Local, "four".to_string())]);
+    assert_eq!(rline.history(0).unwrap(), "three");
+}
+
+#[test]
+fn same_history_lines_are_equal() {
+    let mut rline = RingLine::<4, 8>::new();
+    rline.append_local_line("ls").unwrap();
+    rline.append_local_line("ls").unwrap();
+    rline.append_local_line("cd").unwrap();
+    assert_eq!(rline.history(1).unwrap(), rline.history(2).unwrap());
+    assert_ne!(rline.history(0).unwrap(), rline.history(1).unwrap());
+}
+
+#[test]
+fn timestamps() {
+    // Create a ringline buffer with 16 characters per line, and 8 lines
+    let mut ringline = RingLine::<8, 16>::new();
+
+    ringline.append_local_line("untimed").unwrap();
+    b"a long remote line".iter().for_each(|c| {
+        ringline.append_remote_char(*c).unwrap();
+    });
+    ringline.submit_remote_editing_at(1234);
+    b"local".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    ringline.submit_local_editing_at(5678);
+
+    let stamps = ringline
+        .iter_history()
+        .map(|l| (l.as_str(), l.timestamp()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        stamps,
+        [
+            ("local", Some(5678)),
+            ("ne", Some(1234)),
+            ("a long remote li", Some(1234)),
+            ("untimed", None),
+        ]
+    );
+
+    // Editing lines don't have a timestamp yet
+    b"typing".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    assert_eq!(
+        ringline.iter_local_editing().next().unwrap().timestamp(),
+        None
+    );
+}
+
 fn dump_to_string<const L: usize, const C: usize>(ringline: &RingLine<L, C>) -> String {
     let mut out = String::new();
     writeln!(&mut out, "====").unwrap();
@@ -818,6 +2648,9 @@ fn dump_to_string<const L: usize, const C: usize>(ringline: &RingLine<L, C>) ->
             Source::Remote => {
                 writeln!(&mut out, "R. | {} |", item.as_str()).unwrap();
             }
+            Source::System => {
+                writeln!(&mut out, "S. | {} |", item.as_str()).unwrap();
+            }
         }
     }
 
@@ -845,3 +2678,11 @@ fn dump_to_string<const L: usize, const C: usize>(ringline: &RingLine<L, C>) ->
 
     out
 }
+
+fn editing_to_string<const L: usize, const C: usize>(ringline: &RingLine<L, C>) -> String {
+    ringline
+        .iter_local_editing()
+        .rev()
+        .map(|l| l.as_str())
+        .collect()
+}
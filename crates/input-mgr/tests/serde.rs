@@ -0,0 +1,141 @@
+#![cfg(feature = "serde")]
+
+use input_mgr::{RingLine, Source};
+
+fn history<const L: usize, const C: usize>(
+    ringline: &RingLine<L, C>,
+) -> Vec<(u64, Source, String)> {
+    ringline
+        .iter_history_seq()
+        .map(|(seq, l)| (seq, l.status(), l.as_str().to_string()))
+        .collect()
+}
+
+fn populated() -> RingLine<8, 16> {
+    let mut ringline = RingLine::<8, 16>::new();
+    for i in 0..6 {
+        format!("remote {i}").bytes().for_each(|c| {
+            ringline.append_remote_char(c).unwrap();
+        });
+        ringline.submit_remote_editing();
+        format!("local {i} wraps past sixteen")
+            .bytes()
+            .for_each(|c| {
+                ringline.append_local_char(c).unwrap();
+            });
+        ringline.submit_local_editing();
+    }
+    b"still typing".iter().for_each(|c| {
+        ringline.append_local_char(*c).unwrap();
+    });
+    ringline
+}
+
+#[test]
+fn round_trip() {
+    let ringline = populated();
+    let blob = postcard::to_allocvec(&ringline).unwrap();
+    let restored: RingLine<8, 16> = postcard::from_bytes(&blob).unwrap();
+
+    assert_eq!(history(&ringline), history(&restored));
+    assert_eq!(
+        restored.iter_local_editing().next().unwrap().as_str(),
+        "still typing"
+    );
+    assert_eq!(restored.iter_remote_editing().count(), 0);
+
+    // The restored buffer keeps working like the original
+    let mut restored = restored;
+    restored.submit_local_editing();
+    assert_eq!(
+        restored
+            .iter_history_seq()
+            .next()
+            .map(|(seq, l)| (seq, l.as_str())),
+        ringline
+            .iter_history_seq()
+            .next()
+            .map(|(seq, _)| (seq + 1, "still typing")),
+    );
+}
+
+#[test]
+fn round_trip_continuations() {
+    let mut ringline = RingLine::<4, 4>::new();
+    ringline.append_local_line("abcdefg").unwrap();
+    ringline.append_local_line("hi").unwrap();
+    let blob = postcard::to_allocvec(&ringline).unwrap();
+    let restored: RingLine<4, 4> = postcard::from_bytes(&blob).unwrap();
+
+    let flags = restored
+        .iter_history()
+        .map(|l| l.is_continuation())
+        .collect::<Vec<_>>();
+    assert_eq!(flags, [false, true, false]);
+    assert_eq!(restored, ringline);
+}
+
+#[test]
+fn round_trip_empty() {
+    let ringline = RingLine::<4, 8>::new();
+    let blob = postcard::to_allocvec(&ringline).unwrap();
+    let restored: RingLine<4, 8> = postcard::from_bytes(&blob).unwrap();
+    assert_eq!(restored.iter_history().count(), 0);
+    assert_eq!(restored.iter_local_editing().count(), 0);
+}
+
+#[test]
+fn reject_corrupt_ordering() {
+    let ringline = populated();
+    let mut blob = postcard::to_allocvec(&ringline).unwrap();
+
+    // The blob ends with the line indexes, each a single byte varint for L=8,
+    // followed by the next sequence number and soft line limit (also a single
+    // byte each here). Duplicating an index must be rejected rather than
+    // aliasing a line.
+    let last_idx = blob.len() - 3;
+    blob[last_idx] = blob[last_idx - 1];
+    assert!(postcard::from_bytes::<RingLine<8, 16>>(&blob).is_err());
+
+    // Out of range indexes too
+    blob[last_idx] = 8;
+    assert!(postcard::from_bytes::<RingLine<8, 16>>(&blob).is_err());
+}
+
+#[test]
+fn soft_limit() {
+    let mut ringline = RingLine::<4, 8>::new();
+    ringline.set_soft_line_limit(5);
+    let mut blob = postcard::to_allocvec(&ringline).unwrap();
+    let restored: RingLine<4, 8> = postcard::from_bytes(&blob).unwrap();
+    assert_eq!(restored.soft_line_limit(), 5);
+
+    // The limit can't be larger than `C`
+    let last = blob.len() - 1;
+    blob[last] = 9;
+    assert!(postcard::from_bytes::<RingLine<4, 8>>(&blob).is_err());
+}
+
+#[test]
+fn reject_overlong_line() {
+    let ringline = populated();
+    let blob = postcard::to_allocvec(&ringline).unwrap();
+
+    // Lines that are longer than `C` don't fit
+    assert!(postcard::from_bytes::<RingLine<8, 8>>(&blob).is_err());
+}
+
+#[test]
+fn round_trip_prompt() {
+    let mut ringline = RingLine::<4, 8>::new();
+    ringline.set_local_prompt("$ ").unwrap();
+    ringline.append_local_char(b'x').unwrap();
+    let blob = postcard::to_allocvec(&ringline).unwrap();
+    let mut restored: RingLine<4, 8> = postcard::from_bytes(&blob).unwrap();
+
+    // The prompt is still protected
+    restored.pop_local_char();
+    restored.pop_local_char();
+    let editing = restored.iter_local_editing().next().unwrap();
+    assert_eq!((editing.as_str(), editing.prefix_len()), ("$ ", 2));
+}
@@ -61,8 +61,11 @@ fn main() {
     window.limit_update_rate(Some(Duration::from_micros(1_000_000 / 60)));
 
     let style = ring_drawer::BwStyle {
-        background: Rgb888::BLACK,
-        font: MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::WHITE),
+        wrap: ring_drawer::WrapMode::WordSoft,
+        ..ring_drawer::BwStyle::new(
+            Rgb888::BLACK,
+            MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::WHITE),
+        )
     };
 
     let mut rline = RingLine::<16, 48>::new();
@@ -60,18 +60,19 @@ fn main() {
         Window::new("Test - ESC to exit", DISP_PIXELS_X, DISP_PIXELS_Y, options).unwrap();
     window.limit_update_rate(Some(Duration::from_micros(1_000_000 / 60)));
 
-    let style = ring_drawer::ColorStyle {
-        background: Rgb888::BLACK,
-        local_editing_font: MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::WHITE),
-        remote_editing_font: MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::WHITE),
-        local_history_font: MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::BLACK),
-        remote_history_font: MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::BLACK),
-        local_editing_background: Rgb888::CSS_DARK_BLUE,
-        remote_editing_background: Rgb888::CSS_DARK_GREEN,
-        local_history_background: Rgb888::CSS_LIGHT_BLUE,
-        remote_history_background: Rgb888::CSS_LIGHT_GREEN,
-        margin_chars: 1,
-    };
+    let style =
+        ring_drawer::ColorStyleBuilder::new(&PROFONT_12_POINT, Rgb888::WHITE, Rgb888::BLACK)
+            .with_history_fonts(
+                MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::BLACK),
+                MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::BLACK),
+            )
+            .with_editing_backgrounds(Rgb888::CSS_DARK_BLUE, Rgb888::CSS_DARK_GREEN)
+            .with_history_backgrounds(Rgb888::CSS_LIGHT_BLUE, Rgb888::CSS_LIGHT_GREEN)
+            .with_system_background(Rgb888::CSS_DIM_GRAY)
+            .with_margin_chars(1)
+            .with_alignment(ring_drawer::Alignment::BySource)
+            .with_wrap(ring_drawer::WrapMode::WordSoft)
+            .build();
 
     let mut rline = RingLine::<16, 48>::new();
 
@@ -1,12 +1,76 @@
 #![cfg_attr(not(any(test, feature = "use-std")), no_std)]
 
 use embedded_graphics::{
-    mono_font::MonoTextStyle,
+    draw_target::DrawTargetExt,
+    mono_font::{MonoFont, MonoTextStyle},
     prelude::{DrawTarget, Drawable, PixelColor, Point, Size},
-    primitives::{PrimitiveStyleBuilder, Rectangle, StyledDrawable},
+    primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StyledDrawable},
     text::Text,
 };
-use input_mgr::{RingLine, Source};
+use input_mgr::{DiffResult, DisplayLine, Region, RingSource, Source};
+
+/// How a line that is wider than the drawable area is split into rows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Split at the last column that fits, even if that is mid-word
+    CharHard,
+    /// Split at the last space that fits, falling back to `CharHard` for
+    /// words longer than a whole row
+    WordSoft,
+}
+
+/// Iterator over the rows of a single line, top to bottom
+#[derive(Clone)]
+struct WrapRows<'a> {
+    rest: &'a str,
+    cols: usize,
+    mode: WrapMode,
+    done: bool,
+}
+
+impl<'a> WrapRows<'a> {
+    fn new(text: &'a str, cols: usize, mode: WrapMode) -> Self {
+        Self {
+            rest: text,
+            cols: cols.max(1),
+            mode,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for WrapRows<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.rest.len() <= self.cols {
+            self.done = true;
+            return Some(self.rest);
+        }
+
+        let split = match self.mode {
+            WrapMode::CharHard => self.cols,
+            WrapMode::WordSoft => self.rest.as_bytes()[..=self.cols]
+                .iter()
+                .rposition(|b| *b == b' ')
+                .filter(|pos| *pos != 0)
+                .unwrap_or(self.cols),
+        };
+        let (row, rest) = self.rest.split_at(split);
+        let (row, rest) = match self.mode {
+            WrapMode::CharHard => (row, rest),
+            WrapMode::WordSoft => (row.trim_end_matches(' '), rest.trim_start_matches(' ')),
+        };
+        self.rest = rest;
+        if self.rest.is_empty() {
+            self.done = true;
+        }
+        Some(row)
+    }
+}
 
 #[derive(Clone)]
 pub struct ColorStyle<'font, ColorKind: PixelColor> {
@@ -15,118 +79,743 @@ pub struct ColorStyle<'font, ColorKind: PixelColor> {
     pub remote_editing_font: MonoTextStyle<'font, ColorKind>,
     pub local_history_font: MonoTextStyle<'font, ColorKind>,
     pub remote_history_font: MonoTextStyle<'font, ColorKind>,
+    pub system_history_font: MonoTextStyle<'font, ColorKind>,
     pub local_editing_background: ColorKind,
     pub remote_editing_background: ColorKind,
     pub local_history_background: ColorKind,
     pub remote_history_background: ColorKind,
+    pub system_history_background: ColorKind,
     pub margin_chars: u32,
+    /// Number of history lines (newest first) to skip before drawing
+    pub scroll_offset: usize,
+    pub wrap: WrapMode,
+    pub alignment: Alignment,
+    pub direction: Direction,
 }
 
-pub fn drawer_color<'font, ColorKind, Display, const WIDTH: usize, const HEIGHT: usize>(
-    disp: &mut Display,
-    rline: &RingLine<HEIGHT, WIDTH>,
+impl<'font, ColorKind: PixelColor> ColorStyle<'font, ColorKind> {
+    /// The font used for a line from `source` in `region`
+    ///
+    /// Editing lines use the font of their region, whatever their source. Free
+    /// lines are treated as history lines.
+    pub fn font(&self, source: Source, region: Region) -> MonoTextStyle<'font, ColorKind> {
+        match (region, source) {
+            (Region::Local, _) => self.local_editing_font,
+            (Region::Remote, _) => self.remote_editing_font,
+            (Region::History | Region::Free, Source::Local) => self.local_history_font,
+            (Region::History | Region::Free, Source::Remote) => self.remote_history_font,
+            (Region::History | Region::Free, Source::System) => self.system_history_font,
+        }
+    }
+
+    /// The widest character, including spacing, of all the fonts in pixels.
+    /// This is the unit of `margin_chars`.
+    pub fn max_char_width(&self) -> u32 {
+        [
+            self.local_editing_font,
+            self.remote_editing_font,
+            self.local_history_font,
+            self.remote_history_font,
+            self.system_history_font,
+        ]
+        .iter()
+        .map(|font| char_width(font))
+        .max()
+        .unwrap_or(0)
+    }
+
+    /// The height in pixels of one row of a line from `source` in `region`,
+    /// see [ColorStyle::font()]
+    pub fn row_height(&self, source: Source, region: Region) -> u32 {
+        char_height(&self.font(source, region))
+    }
+}
+
+/// The horizontal advance of one character of `font` in pixels
+fn char_width<ColorKind: PixelColor>(font: &MonoTextStyle<'_, ColorKind>) -> u32 {
+    font.font.character_size.width + font.font.character_spacing
+}
+
+/// The height of one row of `font` in pixels
+fn char_height<ColorKind: PixelColor>(font: &MonoTextStyle<'_, ColorKind>) -> u32 {
+    font.font.character_size.height
+}
+
+/// Does `font` have glyphs with no width or height? Nothing can be drawn with
+/// such a font, embedded-graphics would even divide by zero looking up glyphs.
+fn is_degenerate<ColorKind: PixelColor>(font: &MonoTextStyle<'_, ColorKind>) -> bool {
+    font.font.character_size.width == 0 || font.font.character_size.height == 0
+}
+
+/// Builds a [ColorStyle], starting from a single font and color pair
+///
+/// Every font starts as `font` in the foreground color, and every background
+/// starts as the background color, so only the fields that differ need setting.
+#[derive(Clone)]
+pub struct ColorStyleBuilder<'font, ColorKind: PixelColor> {
     style: ColorStyle<'font, ColorKind>,
+}
+
+impl<'font, ColorKind: PixelColor> ColorStyleBuilder<'font, ColorKind> {
+    pub fn new(font: &'font MonoFont<'font>, foreground: ColorKind, background: ColorKind) -> Self {
+        let font = MonoTextStyle::new(font, foreground);
+        Self {
+            style: ColorStyle {
+                background,
+                local_editing_font: font,
+                remote_editing_font: font,
+                local_history_font: font,
+                remote_history_font: font,
+                system_history_font: font,
+                local_editing_background: background,
+                remote_editing_background: background,
+                local_history_background: background,
+                remote_history_background: background,
+                system_history_background: background,
+                margin_chars: 0,
+                scroll_offset: 0,
+                wrap: WrapMode::CharHard,
+                alignment: Alignment::FullWidth,
+                direction: Direction::BottomUp,
+            },
+        }
+    }
+
+    /// Sets the background of the whole display, outside of any bubble
+    pub fn with_background(mut self, background: ColorKind) -> Self {
+        self.style.background = background;
+        self
+    }
+
+    /// Sets every font at once
+    pub fn uniform_font(mut self, font: MonoTextStyle<'font, ColorKind>) -> Self {
+        self.style.local_editing_font = font;
+        self.style.remote_editing_font = font;
+        self.style.local_history_font = font;
+        self.style.remote_history_font = font;
+        self.style.system_history_font = font;
+        self
+    }
+
+    pub fn with_editing_fonts(
+        mut self,
+        local: MonoTextStyle<'font, ColorKind>,
+        remote: MonoTextStyle<'font, ColorKind>,
+    ) -> Self {
+        self.style.local_editing_font = local;
+        self.style.remote_editing_font = remote;
+        self
+    }
+
+    pub fn with_history_fonts(
+        mut self,
+        local: MonoTextStyle<'font, ColorKind>,
+        remote: MonoTextStyle<'font, ColorKind>,
+    ) -> Self {
+        self.style.local_history_font = local;
+        self.style.remote_history_font = remote;
+        self
+    }
+
+    pub fn with_system_font(mut self, font: MonoTextStyle<'font, ColorKind>) -> Self {
+        self.style.system_history_font = font;
+        self
+    }
+
+    pub fn with_editing_backgrounds(mut self, local: ColorKind, remote: ColorKind) -> Self {
+        self.style.local_editing_background = local;
+        self.style.remote_editing_background = remote;
+        self
+    }
+
+    pub fn with_history_backgrounds(mut self, local: ColorKind, remote: ColorKind) -> Self {
+        self.style.local_history_background = local;
+        self.style.remote_history_background = remote;
+        self
+    }
+
+    pub fn with_system_background(mut self, background: ColorKind) -> Self {
+        self.style.system_history_background = background;
+        self
+    }
+
+    pub fn with_margin_chars(mut self, margin_chars: u32) -> Self {
+        self.style.margin_chars = margin_chars;
+        self
+    }
+
+    pub fn with_scroll_offset(mut self, scroll_offset: usize) -> Self {
+        self.style.scroll_offset = scroll_offset;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.style.wrap = wrap;
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.style.alignment = alignment;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.style.direction = direction;
+        self
+    }
+
+    pub fn build(self) -> ColorStyle<'font, ColorKind> {
+        self.style
+    }
+}
+
+/// Bookkeeping about what a drawer call actually put on screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    /// Number of history lines drawn, not counting any skipped by `scroll_offset`
+    pub history_lines_drawn: usize,
+    /// Number of local and remote editing lines drawn
+    pub editing_lines_drawn: usize,
+    /// Drawing stopped early because a line did not fit on screen. In
+    /// [drawer_color], the visible rows of that line are still drawn.
+    pub truncated: bool,
+    /// Height in pixels of the local and remote editing lines, as needed by
+    /// [DirtySet::from_diff()]. Only set by [drawer_color] and
+    /// [drawer_color_partial].
+    pub editing_height: u32,
+}
+
+/// Scrollback position kept between frames by [drawer_color_view] and
+/// [drawer_bw_view]
+///
+/// While `follow_tail` is set, the view sticks to the NEWEST history line, so new
+/// output stays in view. Scrolling up stops following, and scrolling back down to
+/// the bottom follows again, like a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewState {
+    /// Number of history lines (newest first) to skip before drawing
+    pub scroll_offset: usize,
+    /// Reset `scroll_offset` to 0 before each frame is drawn
+    pub follow_tail: bool,
+}
+
+impl ViewState {
+    /// A view following the NEWEST history line
+    pub const fn new() -> Self {
+        Self {
+            scroll_offset: 0,
+            follow_tail: true,
+        }
+    }
+
+    /// Scrolls `lines` towards OLDER history, no longer following the tail
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_add(lines);
+        self.follow_tail = false;
+    }
+
+    /// Scrolls `lines` towards NEWER history, following the tail again once
+    /// the bottom is reached
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        self.follow_tail = self.scroll_offset == 0;
+    }
+
+    /// Jumps back to the NEWEST history line, and follows it
+    pub fn jump_to_bottom(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Brings `scroll_offset` up to date for a frame with `history_len` lines of
+    /// history, returning the offset to draw with
+    fn update(&mut self, history_len: usize) -> usize {
+        if self.follow_tail {
+            self.scroll_offset = 0;
+        } else {
+            // Don't scroll past the OLDEST line
+            self.scroll_offset = self.scroll_offset.min(history_len.saturating_sub(1));
+        }
+        self.scroll_offset
+    }
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The lines that [drawer_color_partial] needs to redraw
+///
+/// Usually built each frame with [DirtySet::from_diff()], from the [DiffResult]
+/// between the [RingLine] and a [SnapshotBuf] taken when the last frame was
+/// drawn, along with the [DrawStats] returned for that frame.
+///
+/// [RingLine]: input_mgr::RingLine
+/// [SnapshotBuf]: input_mgr::SnapshotBuf
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirtySet<const L: usize> {
+    all: bool,
+    editing: bool,
+    history: [bool; L],
+    /// The [DrawStats::editing_height] of the last frame, if the editing region
+    /// needs redrawing
+    editing_height: Option<u32>,
+}
+
+impl<const L: usize> DirtySet<L> {
+    /// Nothing needs to be redrawn
+    pub const fn clean() -> Self {
+        Self {
+            all: false,
+            editing: false,
+            history: [false; L],
+            editing_height: None,
+        }
+    }
+
+    /// Everything needs to be redrawn, as for the first frame
+    pub const fn all() -> Self {
+        Self {
+            all: true,
+            ..Self::clean()
+        }
+    }
+
+    /// The lines that changed in `diff`, where `last` is from drawing the
+    /// snapshot that `diff` was taken against
+    ///
+    /// If any history lines were added or evicted, every line has moved, so
+    /// everything is marked.
+    pub fn from_diff(diff: &DiffResult<L>, last: &DrawStats) -> Self {
+        if diff.added() != 0 || diff.evicted() != 0 {
+            return Self::all();
+        }
+        let mut dirty = Self::clean();
+        if diff.editing_changed() {
+            dirty.mark_editing(last);
+        }
+        diff.iter_modified().for_each(|idx| dirty.mark_history(idx));
+        dirty
+    }
+
+    /// Mark the local and remote editing lines, where `last` is from drawing the
+    /// previous frame
+    pub fn mark_editing(&mut self, last: &DrawStats) {
+        self.editing = true;
+        self.editing_height = Some(last.editing_height);
+    }
+
+    /// Mark the history line at `idx`, where `0` is the NEWEST line
+    pub fn mark_history(&mut self, idx: usize) {
+        if let Some(dirty) = self.history.get_mut(idx) {
+            *dirty = true;
+        }
+    }
+
+    /// Does the history line at `idx` need to be redrawn?
+    pub fn is_history_dirty(&self, idx: usize) -> bool {
+        self.all || self.history.get(idx).copied().unwrap_or(false)
+    }
+
+    /// Do the editing lines need to be redrawn?
+    pub fn is_editing_dirty(&self) -> bool {
+        self.all || self.editing
+    }
+}
+
+/// Horizontal placement of history bubbles in [drawer_color]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    /// Every bubble spans the full width between the margins
+    FullWidth,
+    /// Bubbles are sized to their text, with local lines on the left,
+    /// remote lines on the right, and system lines centered, like a
+    /// messaging app
+    BySource,
+}
+
+/// Which way the drawers stack lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Editing lines are pinned to the bottom, with history growing upwards,
+    /// like a terminal
+    BottomUp,
+    /// Editing lines are pinned to the top, with history growing downwards,
+    /// like a notification feed
+    TopDown,
+}
+
+/// Which edge of the screen lines of one source grow from in [drawer_bw]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Text starts just after the left gutter, like a terminal
+    Left,
+    /// Text ends just before the right gutter, with the left gutter drawn
+    /// right before the text, like "your" messages in an SMS app
+    Right,
+}
+
+/// Tracks where the next line goes as lines are drawn, NEWEST first
+#[derive(Clone, Copy, Debug)]
+struct RowCursor {
+    /// The edge of the space that has not been drawn to yet
+    y: u32,
+    height: u32,
+    direction: Direction,
+}
+
+impl RowCursor {
+    fn new(height: u32, direction: Direction) -> Self {
+        let y = match direction {
+            Direction::BottomUp => height,
+            Direction::TopDown => 0,
+        };
+        Self {
+            y,
+            height,
+            direction,
+        }
+    }
+
+    /// The number of pixels left to draw into
+    fn room(&self) -> u32 {
+        match self.direction {
+            Direction::BottomUp => self.y,
+            Direction::TopDown => self.height.saturating_sub(self.y),
+        }
+    }
+
+    /// Claims `height` pixels for the next line, returning its top edge, or `None`
+    /// if there is not enough room left
+    fn take(&mut self, height: u32) -> Option<u32> {
+        if height > self.room() {
+            return None;
+        }
+        match self.direction {
+            Direction::BottomUp => {
+                self.y -= height;
+                Some(self.y)
+            }
+            Direction::TopDown => {
+                let top = self.y;
+                self.y += height;
+                Some(top)
+            }
+        }
+    }
+}
+
+/// Where [place_bubble] put a line
+#[derive(Clone, Copy, Debug)]
+struct Placement {
+    y_top: u32,
+    height: u32,
+    /// The number of wrapped rows of the line that are off screen, at the start
+    hidden: usize,
+    visible_rows: u32,
+    /// Did the whole line fit on screen?
+    complete: bool,
+}
+
+/// Claims the space for one line as a bubble in the next space of `cursor`,
+/// without drawing anything
+///
+/// Returns `None` if not even one row of the line fits.
+fn place_bubble<ColorKind: PixelColor>(
+    text: &str,
+    font: MonoTextStyle<'_, ColorKind>,
+    width: u32,
+    cursor: &mut RowCursor,
+    wrap: WrapMode,
+) -> Option<Placement> {
+    if is_degenerate(&font) {
+        return None;
+    }
+    let char_pixels_x = char_width(&font);
+    let char_pixels_y = char_height(&font);
+    let cols = (width / char_pixels_x.max(1)) as usize;
+
+    // Only draw the rows that fit on screen. Drawing bottom up, those are the
+    // last rows of the line, top down they are the first.
+    let total_rows = WrapRows::new(text, cols, wrap).count() as u32;
+    let visible_rows = total_rows.min(cursor.room() / char_pixels_y.max(1));
+    if visible_rows == 0 {
+        return None;
+    }
+    let height = visible_rows * char_pixels_y;
+    let y_top = cursor.take(height)?;
+    let hidden = match cursor.direction {
+        Direction::BottomUp => (total_rows - visible_rows) as usize,
+        Direction::TopDown => 0,
+    };
+    Some(Placement {
+        y_top,
+        height,
+        hidden,
+        visible_rows,
+        complete: visible_rows == total_rows,
+    })
+}
+
+/// Draws one line as a bubble where [place_bubble] put it
+#[allow(clippy::too_many_arguments)]
+fn paint_bubble<ColorKind, Display>(
+    disp: &mut Display,
+    text: &str,
+    font: MonoTextStyle<'_, ColorKind>,
+    bkgd: &PrimitiveStyle<ColorKind>,
+    x: u32,
+    width: u32,
+    placed: &Placement,
+    wrap: WrapMode,
 ) -> Result<(), <Display as DrawTarget>::Error>
 where
     ColorKind: PixelColor,
     Display: DrawTarget<Color = ColorKind>,
 {
-    let full_display = disp.bounding_box();
-
-    let local_edit_char_pixels_y = style.local_editing_font.font.character_size.height;
-    let remote_edit_char_pixels_y = style.remote_editing_font.font.character_size.height;
-    let local_hist_char_pixels_y = style.local_history_font.font.character_size.height;
-    let remote_hist_char_pixels_y = style.remote_history_font.font.character_size.height;
+    let char_pixels_x = char_width(&font);
+    let char_pixels_y = char_height(&font);
+    let cols = (width / char_pixels_x.max(1)) as usize;
+    let rows = WrapRows::new(text, cols, wrap)
+        .skip(placed.hidden)
+        .take(placed.visible_rows as usize);
 
-    // Blank the background
-    let mut y_idx: u32 = full_display.size.height;
-    let x_width = full_display.size.width;
-    disp.fill_solid(&full_display, style.background)?;
+    let bar = Rectangle::new(
+        Point {
+            x: x as i32,
+            y: placed.y_top as i32,
+        },
+        Size {
+            width,
+            height: placed.height,
+        },
+    );
+    bar.draw_styled(bkgd, disp)?;
 
-    let left_margin_px = if style.margin_chars != 0 {
-        let local_edit_char_pixels_x = style.local_editing_font.font.character_size.width
-            + style.local_editing_font.font.character_spacing;
-        let remote_edit_char_pixels_x = style.remote_editing_font.font.character_size.width
-            + style.remote_editing_font.font.character_spacing;
-        let local_hist_char_pixels_x = style.local_history_font.font.character_size.width
-            + style.local_history_font.font.character_spacing;
-        let remote_hist_char_pixels_x = style.remote_history_font.font.character_size.width
-            + style.remote_history_font.font.character_spacing;
-        let largest_width = [
-            local_edit_char_pixels_x,
-            remote_edit_char_pixels_x,
-            local_hist_char_pixels_x,
-            remote_hist_char_pixels_x,
-        ]
-        .into_iter()
-        .max()
-        .unwrap_or(0);
+    for (i, row) in rows.enumerate() {
+        let row_y = placed.y_top + (i as u32 * char_pixels_y);
+        Text::new(
+            row,
+            Point {
+                x: x as i32,
+                y: (row_y + font.font.baseline) as i32,
+            },
+            font,
+        )
+        .draw(disp)?;
+    }
+    Ok(())
+}
 
-        largest_width * style.margin_chars
-    } else {
-        0
+/// Draws one line as a bubble in the next space of `cursor`.
+///
+/// The bubble is one row of `font` tall for each row the text wraps to. Returns
+/// the top edge of the bubble, or `None` if it did not entirely fit on screen, in
+/// which case only the rows nearest the editing lines that fit were drawn.
+#[allow(clippy::too_many_arguments)]
+fn draw_bubble<ColorKind, Display>(
+    disp: &mut Display,
+    text: &str,
+    font: MonoTextStyle<'_, ColorKind>,
+    bkgd: &PrimitiveStyle<ColorKind>,
+    x: u32,
+    width: u32,
+    cursor: &mut RowCursor,
+    wrap: WrapMode,
+) -> Result<Option<u32>, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+{
+    let Some(placed) = place_bubble(text, font, width, cursor, wrap) else {
+        return Ok(None);
     };
+    paint_bubble(disp, text, font, bkgd, x, width, &placed, wrap)?;
+    Ok(placed.complete.then_some(placed.y_top))
+}
+
+pub fn drawer_color<'font, ColorKind, Display, R>(
+    disp: &mut Display,
+    rline: &R,
+    style: ColorStyle<'font, ColorKind>,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    draw_color(disp, rline, style, None::<&DirtySet<0>>)
+}
+
+/// Like [drawer_color()], but only redraws the lines marked in `dirty`
+///
+/// Every line is placed exactly as [drawer_color()] places it, but only the rows
+/// of dirty lines are cleared and redrawn, leaving the rest of the display as it
+/// was. The display must still hold the previous frame, drawn by either drawer.
+///
+/// Lines below a dirty history line (or above it, drawing [Direction::TopDown])
+/// are redrawn too, as their position depends on its height. If `dirty` marks
+/// everything, or the editing region changed height, this is the same as
+/// [drawer_color()].
+pub fn drawer_color_partial<'font, ColorKind, Display, R, const L: usize>(
+    disp: &mut Display,
+    rline: &R,
+    style: ColorStyle<'font, ColorKind>,
+    dirty: &DirtySet<L>,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    draw_color(disp, rline, style, Some(dirty))
+}
+
+/// Which parts of a bubble [draw_color] draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Paint {
+    /// Only claim the space for it
+    Skip,
+    /// Draw it over the already blank background
+    Bubble,
+    /// Blank the full width of its rows, then draw it
+    Row,
+}
+
+fn draw_color<'font, ColorKind, Display, R, const L: usize>(
+    disp: &mut Display,
+    rline: &R,
+    style: ColorStyle<'font, ColorKind>,
+    dirty: Option<&DirtySet<L>>,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    let full_display = disp.bounding_box();
+    let mut stats = DrawStats::default();
+
+    let mut cursor = RowCursor::new(full_display.size.height, style.direction);
+    let x_width = full_display.size.width;
+    let clear_style = PrimitiveStyle::with_fill(style.background);
+
+    let left_margin_px = style.max_char_width() * style.margin_chars;
 
     let width_margin = if style.margin_chars != 0 {
-        x_width - (2 * left_margin_px)
+        x_width.saturating_sub(left_margin_px.saturating_mul(2))
     } else {
         x_width
     };
 
+    // The margins leave no room for text, so none of the lines fit
+    if width_margin == 0 {
+        disp.fill_solid(&full_display, style.background)?;
+        let lines = rline.iter_local_editing().len()
+            + rline.iter_remote_editing().len()
+            + rline.iter_history().len();
+        stats.truncated = lines != 0;
+        return Ok(stats);
+    }
+
+    // Places a bubble, and draws as much of it as `paint` asks for
+    let bubble = |disp: &mut Display,
+                  cursor: &mut RowCursor,
+                  text: &str,
+                  font: MonoTextStyle<'font, ColorKind>,
+                  bkgd: &PrimitiveStyle<ColorKind>,
+                  (x, width): (u32, u32),
+                  paint: Paint| {
+        if paint == Paint::Bubble {
+            return draw_bubble(disp, text, font, bkgd, x, width, cursor, style.wrap);
+        }
+        let Some(placed) = place_bubble(text, font, width, cursor, style.wrap) else {
+            return Ok(None);
+        };
+        if paint == Paint::Row {
+            Rectangle::new(
+                Point::new(0, placed.y_top as i32),
+                Size::new(x_width, placed.height),
+            )
+            .draw_styled(&clear_style, disp)?;
+            paint_bubble(disp, text, font, bkgd, x, width, &placed, style.wrap)?;
+        }
+        Ok(placed.complete.then_some(placed.y_top))
+    };
+
+    // Only redraw part of the screen if the editing region is the same height
+    // as last time, so that every history line is where it was
+    let dirty = dirty.filter(|dirty| {
+        if dirty.all {
+            return false;
+        }
+        if !dirty.editing {
+            return true;
+        }
+        let mut scratch = cursor;
+        let fonts = [style.local_editing_font, style.remote_editing_font];
+        let editing = rline
+            .iter_local_editing()
+            .map(|line| (line, fonts[0]))
+            .chain(rline.iter_remote_editing().map(|line| (line, fonts[1])));
+        for (line, font) in editing {
+            if place_bubble(line.as_str(), font, width_margin, &mut scratch, style.wrap).is_none() {
+                break;
+            }
+        }
+        dirty.editing_height == Some(cursor.room() - scratch.room())
+    });
+    let editing_paint = match dirty {
+        None => Paint::Bubble,
+        Some(dirty) if dirty.is_editing_dirty() => Paint::Row,
+        Some(_) => Paint::Skip,
+    };
+    if dirty.is_none() {
+        // Blank the background
+        disp.fill_solid(&full_display, style.background)?;
+    }
+
     let local_edit_bkgd_style = PrimitiveStyleBuilder::new()
         .fill_color(style.local_editing_background)
         .build();
     for line in rline.iter_local_editing() {
-        y_idx -= local_edit_char_pixels_y;
-        let bar = Rectangle::new(
-            Point {
-                x: left_margin_px as i32,
-                y: y_idx as i32,
-            },
-            Size {
-                width: width_margin,
-                height: local_edit_char_pixels_y,
-            },
-        );
-        bar.draw_styled(&local_edit_bkgd_style, disp)?;
-
-        Text::new(
+        if bubble(
+            disp,
+            &mut cursor,
             line.as_str(),
-            Point {
-                x: left_margin_px as i32,
-                y: (y_idx + style.local_editing_font.font.baseline) as i32,
-            },
             style.local_editing_font,
-        )
-        .draw(disp)?;
+            &local_edit_bkgd_style,
+            (left_margin_px, width_margin),
+            editing_paint,
+        )?
+        .is_none()
+        {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.editing_lines_drawn += 1;
     }
 
     let remote_edit_bkgd_style = PrimitiveStyleBuilder::new()
         .fill_color(style.remote_editing_background)
         .build();
     for line in rline.iter_remote_editing() {
-        y_idx -= remote_edit_char_pixels_y;
-        let bar = Rectangle::new(
-            Point {
-                x: left_margin_px as i32,
-                y: y_idx as i32,
-            },
-            Size {
-                width: width_margin,
-                height: remote_edit_char_pixels_y,
-            },
-        );
-        bar.draw_styled(&remote_edit_bkgd_style, disp)?;
-
-        Text::new(
+        if bubble(
+            disp,
+            &mut cursor,
             line.as_str(),
-            Point {
-                x: left_margin_px as i32,
-                y: (y_idx + style.remote_editing_font.font.baseline) as i32,
-            },
             style.remote_editing_font,
-        )
-        .draw(disp)?;
+            &remote_edit_bkgd_style,
+            (left_margin_px, width_margin),
+            editing_paint,
+        )?
+        .is_none()
+        {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.editing_lines_drawn += 1;
     }
 
     let local_hist_bkgd_style = PrimitiveStyleBuilder::new()
@@ -135,162 +824,1527 @@ where
     let remote_hist_bkgd_style = PrimitiveStyleBuilder::new()
         .fill_color(style.remote_history_background)
         .build();
-    for line in rline.iter_history() {
-        let (line_y, font, bkgd) = match line.status() {
-            Source::Local => (
-                local_hist_char_pixels_y,
-                style.local_history_font,
-                local_hist_bkgd_style,
-            ),
-            Source::Remote => (
-                remote_hist_char_pixels_y,
-                style.remote_history_font,
-                remote_hist_bkgd_style,
-            ),
+    let system_hist_bkgd_style = PrimitiveStyleBuilder::new()
+        .fill_color(style.system_history_background)
+        .build();
+    stats.editing_height = full_display.size.height - cursor.room();
+    let mut history_paint = match dirty {
+        None => Paint::Bubble,
+        Some(_) => Paint::Skip,
+    };
+    for (idx, line) in rline.iter_history().enumerate().skip(style.scroll_offset) {
+        if dirty.is_some_and(|dirty| dirty.is_history_dirty(idx)) {
+            history_paint = Paint::Row;
+        }
+        let (font, bkgd) = match line.status() {
+            Source::Local => (style.local_history_font, &local_hist_bkgd_style),
+            Source::Remote => (style.remote_history_font, &remote_hist_bkgd_style),
+            Source::System => (style.system_history_font, &system_hist_bkgd_style),
         };
 
-        y_idx -= line_y;
-        let bar = Rectangle::new(
-            Point {
-                x: left_margin_px as i32,
-                y: y_idx as i32,
-            },
-            Size {
-                width: width_margin,
-                height: line_y,
-            },
-        );
-        bar.draw_styled(&bkgd, disp)?;
+        let (bubble_x, bubble_width) = match style.alignment {
+            Alignment::FullWidth => (left_margin_px, width_margin),
+            Alignment::BySource => {
+                let char_pixels_x = char_width(&font);
+                let text_px = line.as_str().len() as u32 * char_pixels_x;
+                if text_px >= width_margin {
+                    // Too long to hug a side, fall back to the full width
+                    (left_margin_px, width_margin)
+                } else {
+                    match line.status() {
+                        Source::Local => (left_margin_px, text_px),
+                        Source::Remote => (left_margin_px + width_margin - text_px, text_px),
+                        Source::System => (left_margin_px + (width_margin - text_px) / 2, text_px),
+                    }
+                }
+            }
+        };
 
-        Text::new(
+        if bubble(
+            disp,
+            &mut cursor,
             line.as_str(),
-            Point {
-                x: left_margin_px as i32,
-                y: (y_idx + font.font.baseline) as i32,
-            },
             font,
-        )
-        .draw(disp)?;
+            bkgd,
+            (bubble_x, bubble_width),
+            history_paint,
+        )?
+        .is_none()
+        {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.history_lines_drawn += 1;
     }
 
-    Ok(())
+    // Anything left over may have been covered by lines that have since moved
+    if history_paint == Paint::Row {
+        let room = cursor.room();
+        let y = match style.direction {
+            Direction::BottomUp => 0,
+            Direction::TopDown => full_display.size.height - room,
+        };
+        Rectangle::new(Point::new(0, y as i32), Size::new(x_width, room))
+            .draw_styled(&clear_style, disp)?;
+    }
+
+    Ok(stats)
 }
 
-#[derive(Clone)]
-pub struct BwStyle<'font, ColorKind: PixelColor> {
-    pub background: ColorKind,
-    pub font: MonoTextStyle<'font, ColorKind>,
+/// Like [drawer_color()], but only draws within `area` of the display
+///
+/// Everything, including the background fill, is clipped to `area`, leaving the
+/// rest of the display free for other UI such as a status bar.
+pub fn drawer_color_in<'font, ColorKind, Display, R>(
+    disp: &mut Display,
+    rline: &R,
+    style: ColorStyle<'font, ColorKind>,
+    area: Rectangle,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    drawer_color(&mut disp.cropped(&area), rline, style)
 }
 
-pub fn drawer_bw<'font, ColorKind, Display, const WIDTH: usize, const HEIGHT: usize>(
+/// Like [drawer_color()], but scrolled as set by `view` instead of the style's
+/// `scroll_offset`, see [ViewState]
+pub fn drawer_color_view<'font, ColorKind, Display, R>(
     disp: &mut Display,
-    rline: &RingLine<HEIGHT, WIDTH>,
-    style: BwStyle<'font, ColorKind>,
-) -> Result<(), <Display as DrawTarget>::Error>
+    rline: &R,
+    mut style: ColorStyle<'font, ColorKind>,
+    view: &mut ViewState,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
 where
     ColorKind: PixelColor,
     Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
 {
-    let full_display = disp.bounding_box();
-    let char_pixels_y = style.font.font.character_size.height;
-    let char_pixels_x = style.font.font.character_size.width + style.font.font.character_spacing;
+    style.scroll_offset = view.update(rline.iter_history().len());
+    drawer_color(disp, rline, style)
+}
 
-    // Blank the background
-    let mut y_idx: u32 = full_display.size.height;
-    let x_width = full_display.size.width;
-    let l_gutter = 2 * char_pixels_x;
-    let r_gutter = x_width - (2 * char_pixels_x);
-    disp.fill_solid(&full_display, style.background)?;
+#[derive(Clone)]
+pub struct BwStyle<'font, ColorKind: PixelColor> {
+    pub background: ColorKind,
+    pub font: MonoTextStyle<'font, ColorKind>,
+    /// Number of history lines (newest first) to skip before drawing
+    pub scroll_offset: usize,
+    pub wrap: WrapMode,
+    /// Draw a block cursor after the newest local editing line, or at the start
+    /// of the first row if nothing is being typed. Toggle this between frames to
+    /// make the cursor blink.
+    pub cursor_visible: bool,
+    /// If set, at most this many rows are used for history lines
+    pub max_history_rows: Option<usize>,
+    /// If set, at most this many rows are used for local and remote editing lines
+    pub max_editing_rows: Option<usize>,
+    /// Left and right gutters of local history lines
+    pub local_prefix: &'static str,
+    pub local_suffix: &'static str,
+    /// Left and right gutters of remote history lines
+    pub remote_prefix: &'static str,
+    pub remote_suffix: &'static str,
+    /// Left and right gutters of system history lines
+    pub system_prefix: &'static str,
+    pub system_suffix: &'static str,
+    /// Left and right gutters of local editing lines
+    pub local_edit_prefix: &'static str,
+    pub local_edit_suffix: &'static str,
+    /// Left and right gutters of remote editing lines
+    pub remote_edit_prefix: &'static str,
+    pub remote_edit_suffix: &'static str,
+    /// If set, the history line at this index (newest first, as with
+    /// [RingSource::iter_history]) is drawn inverted
+    pub selected_index: Option<usize>,
+    /// The edge that local lines, and system lines, grow from. When this is
+    /// the same as `remote_side`, lines are drawn as a plain log.
+    pub local_side: Side,
+    /// The edge that remote lines grow from
+    pub remote_side: Side,
+    pub direction: Direction,
+}
 
-    for line in rline.iter_local_editing() {
-        // Bail once we run out of screen
-        y_idx = match y_idx.checked_sub(char_pixels_y) {
-            Some(y) => y,
-            None => return Ok(()),
-        };
+impl<'font, ColorKind: PixelColor> BwStyle<'font, ColorKind> {
+    /// A style with the given colors and font, and defaults for everything else
+    pub fn new(background: ColorKind, font: MonoTextStyle<'font, ColorKind>) -> Self {
+        Self {
+            background,
+            font,
+            scroll_offset: 0,
+            wrap: WrapMode::CharHard,
+            cursor_visible: true,
+            max_history_rows: None,
+            max_editing_rows: None,
+            local_prefix: ">|",
+            local_suffix: "|>",
+            remote_prefix: "<|",
+            remote_suffix: "|<",
+            system_prefix: "*|",
+            system_suffix: "|*",
+            local_edit_prefix: "> ",
+            local_edit_suffix: " #",
+            remote_edit_prefix: "< ",
+            remote_edit_suffix: " #",
+            selected_index: None,
+            direction: Direction::BottomUp,
+            local_side: Side::Left,
+            remote_side: Side::Left,
+        }
+    }
+
+    /// The width in characters of the widest left and right gutters
+    fn gutter_chars(&self) -> (usize, usize) {
+        let prefixes = [
+            self.local_prefix,
+            self.remote_prefix,
+            self.system_prefix,
+            self.local_edit_prefix,
+            self.remote_edit_prefix,
+        ];
+        let suffixes = [
+            self.local_suffix,
+            self.remote_suffix,
+            self.system_suffix,
+            self.local_edit_suffix,
+            self.remote_edit_suffix,
+        ];
+        let widest = |g: [&str; 5]| g.iter().map(|s| s.len()).max().unwrap_or(0);
+        (widest(prefixes), widest(suffixes))
+    }
+}
+
+/// Pixel geometry shared by every row drawn by [drawer_bw]
+struct BwLayout {
+    width: u32,
+    char_pixels_x: u32,
+    char_pixels_y: u32,
+    l_gutter: u32,
+    r_gutter: u32,
+    cols: usize,
+}
+
+/// Limits the number of rows a region of [drawer_bw] may use
+struct RowBudget {
+    left: Option<usize>,
+    exhausted: bool,
+}
+
+impl RowBudget {
+    fn new(max: Option<usize>) -> Self {
+        Self {
+            left: max,
+            exhausted: false,
+        }
+    }
+
+    /// Reserves `rows` rows. Once this fails, every later request fails too, so
+    /// that a short line can't be drawn above a longer one that was skipped.
+    fn take(&mut self, rows: usize) -> bool {
+        if self.exhausted {
+            return false;
+        }
+        match &mut self.left {
+            None => true,
+            Some(left) if *left >= rows => {
+                *left -= rows;
+                true
+            }
+            Some(_) => {
+                self.exhausted = true;
+                false
+            }
+        }
+    }
+}
+
+/// Draws one line, with gutters on every row, in the next space of `cursor`.
+/// A `selected` line is drawn with the foreground and background swapped.
+/// Each row grows from `side`, with the gutters on either side of it.
+///
+/// Returns the top edge of the line, or `None` if it did not fit on screen.
+fn draw_gutter_line<ColorKind, Display>(
+    disp: &mut Display,
+    text: &str,
+    (lgutter, rgutter, side): (&str, &str, Side),
+    style: &BwStyle<'_, ColorKind>,
+    layout: &BwLayout,
+    cursor: &mut RowCursor,
+    selected: bool,
+) -> Result<Option<u32>, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+{
+    let rows = WrapRows::new(text, layout.cols, style.wrap);
 
-        let font_y = (y_idx + style.font.font.baseline) as i32;
+    let height = rows.clone().count() as u32 * layout.char_pixels_y;
+    let y_top = match cursor.take(height) {
+        Some(y) => y,
+        None => return Ok(None),
+    };
+
+    let mut font = style.font;
+    if let (true, Some(color)) = (selected, style.font.text_color) {
+        let bar = Rectangle::new(
+            Point {
+                x: 0,
+                y: y_top as i32,
+            },
+            Size {
+                width: layout.width,
+                height,
+            },
+        );
+        bar.draw_styled(
+            &PrimitiveStyleBuilder::new().fill_color(color).build(),
+            disp,
+        )?;
+        font.text_color = Some(style.background);
+        font.background_color = font.background_color.map(|_| color);
+    }
+
+    for (i, row) in rows.enumerate() {
+        let font_y = (y_top + (i as u32 * layout.char_pixels_y) + style.font.font.baseline) as i32;
+        let (lg_x, text_x) = match side {
+            Side::Left => (0, layout.l_gutter),
+            Side::Right => {
+                let text_x = layout
+                    .r_gutter
+                    .saturating_sub(row.len() as u32 * layout.char_pixels_x)
+                    .max(layout.l_gutter);
+                let lg_x = text_x.saturating_sub(lgutter.len() as u32 * layout.char_pixels_x);
+                (lg_x, text_x)
+            }
+        };
 
         // Left gutter
-        let lgpt = Point { x: 0, y: font_y };
-        Text::new("> ", lgpt, style.font).draw(disp)?;
+        let lgpt = Point {
+            x: lg_x as i32,
+            y: font_y,
+        };
+        Text::new(lgutter, lgpt, font).draw(disp)?;
 
         // Text
         let ltpt = Point {
-            x: l_gutter as i32,
+            x: text_x as i32,
             y: font_y,
         };
-        Text::new(line.as_str(), ltpt, style.font).draw(disp)?;
+        Text::new(row, ltpt, font).draw(disp)?;
 
         // Right gutter
         let rgpt = Point {
-            x: r_gutter as i32,
+            x: layout.r_gutter as i32,
             y: font_y,
         };
-        Text::new(" #", rgpt, style.font).draw(disp)?;
+        Text::new(rgutter, rgpt, font).draw(disp)?;
+    }
+
+    Ok(Some(y_top))
+}
+
+/// One line handed out by [layout_bw], in drawing order
+struct BwLine<'a> {
+    text: &'a str,
+    gutters: (&'static str, &'static str, Side),
+    selected: bool,
+    /// Is this the newest local editing line, that the cursor follows?
+    newest_local: bool,
+}
+
+/// Walks the lines of `rline` in the order [drawer_bw] draws them, keeping to the
+/// row budgets of `style`. `place` draws one line, returning `false` if it did
+/// not fit on screen, which ends the walk.
+fn layout_bw<ColorKind, R, E>(
+    rline: &R,
+    style: &BwStyle<'_, ColorKind>,
+    cols: usize,
+    mut place: impl FnMut(BwLine<'_>) -> Result<bool, E>,
+) -> Result<DrawStats, E>
+where
+    ColorKind: PixelColor,
+    R: RingSource,
+{
+    let mut stats = DrawStats::default();
+    let rows_of = |text: &str| WrapRows::new(text, cols, style.wrap).count();
+    let mut editing_rows = RowBudget::new(style.max_editing_rows);
+    let mut history_rows = RowBudget::new(style.max_history_rows);
+
+    for (i, line) in rline.iter_local_editing().enumerate() {
+        if !editing_rows.take(rows_of(line.as_str())) {
+            stats.truncated = true;
+            break;
+        }
+
+        // Bail once we run out of screen
+        let placed = place(BwLine {
+            text: line.as_str(),
+            gutters: (
+                style.local_edit_prefix,
+                style.local_edit_suffix,
+                style.local_side,
+            ),
+            selected: false,
+            newest_local: i == 0,
+        })?;
+        if !placed {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.editing_lines_drawn += 1;
     }
 
     for line in rline.iter_remote_editing() {
+        if !editing_rows.take(rows_of(line.as_str())) {
+            stats.truncated = true;
+            break;
+        }
+
+        // Bail once we run out of screen
+        let placed = place(BwLine {
+            text: line.as_str(),
+            gutters: (
+                style.remote_edit_prefix,
+                style.remote_edit_suffix,
+                style.remote_side,
+            ),
+            selected: false,
+            newest_local: false,
+        })?;
+        if !placed {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.editing_lines_drawn += 1;
+    }
+
+    for (i, line) in rline.iter_history().enumerate().skip(style.scroll_offset) {
+        let gutters = match line.status() {
+            Source::Local => (style.local_prefix, style.local_suffix, style.local_side),
+            Source::Remote => (style.remote_prefix, style.remote_suffix, style.remote_side),
+            Source::System => (style.system_prefix, style.system_suffix, style.local_side),
+        };
+
+        if !history_rows.take(rows_of(line.as_str())) {
+            stats.truncated = true;
+            break;
+        }
+
         // Bail once we run out of screen
-        y_idx = match y_idx.checked_sub(char_pixels_y) {
+        let placed = place(BwLine {
+            text: line.as_str(),
+            gutters,
+            selected: style.selected_index == Some(i),
+            newest_local: false,
+        })?;
+        if !placed {
+            stats.truncated = true;
+            return Ok(stats);
+        }
+        stats.history_lines_drawn += 1;
+    }
+
+    Ok(stats)
+}
+
+pub fn drawer_bw<'font, ColorKind, Display, R>(
+    disp: &mut Display,
+    rline: &R,
+    style: BwStyle<'font, ColorKind>,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    let full_display = disp.bounding_box();
+    let char_pixels_y = char_height(&style.font);
+    let char_pixels_x = char_width(&style.font);
+
+    // Blank the background
+    let mut cursor = RowCursor::new(full_display.size.height, style.direction);
+    let x_width = full_display.size.width;
+    let (l_chars, r_chars) = style.gutter_chars();
+    let l_gutter = l_chars as u32 * char_pixels_x;
+    let r_gutter = x_width.saturating_sub(r_chars as u32 * char_pixels_x);
+    let layout = BwLayout {
+        width: x_width,
+        char_pixels_x,
+        char_pixels_y,
+        l_gutter,
+        r_gutter,
+        cols: (r_gutter.saturating_sub(l_gutter) / char_pixels_x.max(1)) as usize,
+    };
+    disp.fill_solid(&full_display, style.background)?;
+
+    // A line can't be drawn with this font, so none of them fit
+    if is_degenerate(&style.font) {
+        let lines = rline.iter_local_editing().len()
+            + rline.iter_remote_editing().len()
+            + rline.iter_history().len();
+        return Ok(DrawStats {
+            truncated: lines != 0,
+            ..DrawStats::default()
+        });
+    }
+
+    let cursor_color = style.font.text_color.filter(|_| style.cursor_visible);
+    let draw_cursor = |disp: &mut Display, color, x: u32, y: u32| {
+        let cursor = Rectangle::new(
+            Point {
+                x: x as i32,
+                y: y as i32,
+            },
+            Size {
+                width: char_pixels_x,
+                height: char_pixels_y,
+            },
+        );
+        cursor.draw_styled(
+            &PrimitiveStyleBuilder::new().fill_color(color).build(),
+            disp,
+        )
+    };
+
+    let mut first_row = cursor;
+    let first_row = first_row.take(char_pixels_y);
+
+    let stats = layout_bw(rline, &style, layout.cols, |line| {
+        let y_top = match draw_gutter_line(
+            disp,
+            line.text,
+            line.gutters,
+            &style,
+            &layout,
+            &mut cursor,
+            line.selected,
+        )? {
             Some(y) => y,
-            None => return Ok(()),
+            None => return Ok(false),
         };
 
-        let font_y = (y_idx + style.font.font.baseline) as i32;
+        // The cursor sits where the next character will land: just past the
+        // end of the last row of the newest line, or on its last character if
+        // the row is full. Right-aligned text already ends there, so it sits at
+        // the start of the right gutter instead.
+        if let Some(color) = cursor_color.filter(|_| line.newest_local) {
+            let rows = WrapRows::new(line.text, layout.cols, style.wrap);
+            let row_count = rows.clone().count() as u32;
+            let col = rows.last().map(str::len).unwrap_or(0);
+            let col = col.min(layout.cols.saturating_sub(1)) as u32;
+            let x = match style.local_side {
+                Side::Left => l_gutter + col * char_pixels_x,
+                Side::Right => r_gutter,
+            };
+            draw_cursor(disp, color, x, y_top + (row_count - 1) * char_pixels_y)?;
+        }
+        Ok(true)
+    })?;
 
-        // Left gutter
-        let lgpt = Point { x: 0, y: font_y };
-        Text::new("< ", lgpt, style.font).draw(disp)?;
+    // With nothing typed, the cursor waits at the start of the first row
+    if let Some(color) = cursor_color.filter(|_| rline.iter_local_editing().len() == 0) {
+        if let Some(y) = first_row {
+            let x = match style.local_side {
+                Side::Left => l_gutter,
+                Side::Right => r_gutter,
+            };
+            draw_cursor(disp, color, x, y)?;
+        }
+    }
+    Ok(stats)
+}
 
-        // Text
-        let ltpt = Point {
-            x: l_gutter as i32,
-            y: font_y,
+/// Like [drawer_bw()], but scrolled as set by `view` instead of the style's
+/// `scroll_offset`, see [ViewState]
+pub fn drawer_bw_view<'font, ColorKind, Display, R>(
+    disp: &mut Display,
+    rline: &R,
+    mut style: BwStyle<'font, ColorKind>,
+    view: &mut ViewState,
+) -> Result<DrawStats, <Display as DrawTarget>::Error>
+where
+    ColorKind: PixelColor,
+    Display: DrawTarget<Color = ColorKind>,
+    R: RingSource,
+{
+    style.scroll_offset = view.update(rline.iter_history().len());
+    drawer_bw(disp, rline, style)
+}
+
+/// A grid of characters that [drawer_text] draws into, one byte per cell
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextGrid<const COLS: usize, const ROWS: usize> {
+    cells: [[u8; COLS]; ROWS],
+}
+
+impl<const COLS: usize, const ROWS: usize> TextGrid<COLS, ROWS> {
+    /// A grid filled with spaces
+    pub const fn new() -> Self {
+        Self {
+            cells: [[b' '; COLS]; ROWS],
+        }
+    }
+
+    /// The text of row `idx`, top to bottom, without trailing spaces
+    pub fn row(&self, idx: usize) -> &str {
+        let row = &self.cells[idx];
+        let end = row.iter().rposition(|b| *b != b' ').map_or(0, |e| e + 1);
+        // Only ASCII from `Line`s is ever written
+        core::str::from_utf8(&row[..end]).unwrap_or("")
+    }
+
+    /// Iterates all rows, top to bottom, as with [TextGrid::row()]
+    pub fn rows(&self) -> impl Iterator<Item = &str> + '_ {
+        (0..ROWS).map(|i| self.row(i))
+    }
+
+    fn clear(&mut self) {
+        self.cells = [[b' '; COLS]; ROWS];
+    }
+
+    /// Writes `s` starting at column `x` of row `y`, cut off at the right edge
+    fn put(&mut self, x: usize, y: usize, s: &str) {
+        let row = &mut self.cells[y];
+        for (cell, b) in row.iter_mut().skip(x).zip(s.bytes()) {
+            *cell = b;
+        }
+    }
+}
+
+impl<const COLS: usize, const ROWS: usize> Default for TextGrid<COLS, ROWS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const COLS: usize, const ROWS: usize> core::fmt::Display for TextGrid<COLS, ROWS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, row) in self.rows().enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Draws what [drawer_bw()] would, as text, with one cell of `grid` per
+/// character. Gutters, wrapping, row budgets, direction and sides are all laid
+/// out the same way. The font and colors of `style` are not used, and neither
+/// the cursor nor the selected line are drawn.
+///
+/// Meant for checking the layout in tests, without a framebuffer.
+pub fn drawer_text<ColorKind, R, const COLS: usize, const ROWS: usize>(
+    grid: &mut TextGrid<COLS, ROWS>,
+    rline: &R,
+    style: BwStyle<'_, ColorKind>,
+) -> DrawStats
+where
+    ColorKind: PixelColor,
+    R: RingSource,
+{
+    grid.clear();
+    let mut cursor = RowCursor::new(ROWS as u32, style.direction);
+    let (l_chars, r_chars) = style.gutter_chars();
+    let r_gutter = COLS.saturating_sub(r_chars);
+    let cols = r_gutter.saturating_sub(l_chars);
+
+    let placed = layout_bw(rline, &style, cols, |line| {
+        let (lgutter, rgutter, side) = line.gutters;
+        let rows = WrapRows::new(line.text, cols, style.wrap);
+        let y_top = match cursor.take(rows.clone().count() as u32) {
+            Some(y) => y as usize,
+            None => return Ok::<_, core::convert::Infallible>(false),
         };
-        Text::new(line.as_str(), ltpt, style.font).draw(disp)?;
 
-        // Right gutter
-        let rgpt = Point {
-            x: r_gutter as i32,
-            y: font_y,
+        for (i, row) in rows.enumerate() {
+            let (lg_x, text_x) = match side {
+                Side::Left => (0, l_chars),
+                Side::Right => {
+                    let text_x = r_gutter.saturating_sub(row.len()).max(l_chars);
+                    (text_x.saturating_sub(lgutter.len()), text_x)
+                }
+            };
+            grid.put(lg_x, y_top + i, lgutter);
+            grid.put(text_x, y_top + i, row);
+            grid.put(r_gutter, y_top + i, rgutter);
+        }
+        Ok(true)
+    });
+    match placed {
+        Ok(stats) => stats,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod wrap_tests {
+    use super::{WrapMode, WrapRows};
+
+    fn rows(text: &str, cols: usize, mode: WrapMode) -> Vec<&str> {
+        WrapRows::new(text, cols, mode).collect()
+    }
+
+    #[test]
+    fn fits() {
+        assert_eq!(rows("", 10, WrapMode::WordSoft), [""]);
+        assert_eq!(rows("hello", 10, WrapMode::WordSoft), ["hello"]);
+        assert_eq!(rows("hello", 5, WrapMode::CharHard), ["hello"]);
+    }
+
+    #[test]
+    fn char_hard() {
+        assert_eq!(
+            rows("hello world", 4, WrapMode::CharHard),
+            ["hell", "o wo", "rld"]
+        );
+    }
+
+    #[test]
+    fn word_soft() {
+        assert_eq!(
+            rows("hello big world", 10, WrapMode::WordSoft),
+            ["hello big", "world"]
+        );
+        assert_eq!(
+            rows("hello world", 5, WrapMode::WordSoft),
+            ["hello", "world"]
+        );
+        assert_eq!(
+            rows("....^....^....^", 4, WrapMode::WordSoft),
+            ["....", "^...", ".^..", "..^"]
+        );
+        assert_eq!(rows("a   b", 2, WrapMode::WordSoft), ["a", "b"]);
+    }
+}
+
+#[cfg(test)]
+mod budget_tests {
+    use super::RowBudget;
+
+    #[test]
+    fn unlimited() {
+        let mut budget = RowBudget::new(None);
+        assert!(budget.take(1000));
+        assert!(budget.take(1000));
+    }
+
+    #[test]
+    fn limited() {
+        let mut budget = RowBudget::new(Some(3));
+        assert!(budget.take(2));
+        assert!(!budget.take(2));
+        // Stays exhausted, even for a request that would fit
+        assert!(!budget.take(1));
+    }
+}
+
+#[cfg(test)]
+mod gutter_tests {
+    use embedded_graphics::{mono_font::MonoTextStyle, pixelcolor::BinaryColor};
+    use profont::PROFONT_12_POINT;
+
+    use super::BwStyle;
+
+    #[test]
+    fn widest_gutters() {
+        let mut style = BwStyle::new(
+            BinaryColor::Off,
+            MonoTextStyle::new(&PROFONT_12_POINT, BinaryColor::On),
+        );
+        assert_eq!(style.gutter_chars(), (2, 2));
+
+        style.local_prefix = "you:";
+        style.remote_prefix = "bot:";
+        style.local_suffix = "";
+        assert_eq!(style.gutter_chars(), (4, 2));
+    }
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+        prelude::Point,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_bw, BwStyle};
+
+    #[test]
+    fn inverted() {
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("a").unwrap();
+        rline.append_local_line("b").unwrap();
+
+        let style = BwStyle {
+            selected_index: Some(1),
+            cursor_visible: false,
+            ..BwStyle::new(
+                BinaryColor::Off,
+                MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            )
         };
-        Text::new(" #", rgpt, style.font).draw(disp)?;
+        drawer_bw(&mut disp, &rline, style).unwrap();
+
+        // "b" is newest, on the bottom row and drawn normally. "a" is
+        // selected, so its row is filled with the foreground color.
+        assert_eq!(disp.get_pixel(Point::new(63, 44)), Some(BinaryColor::On));
+        assert_eq!(disp.get_pixel(Point::new(63, 53)), Some(BinaryColor::On));
+        assert_eq!(disp.get_pixel(Point::new(63, 54)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(63, 43)), Some(BinaryColor::Off));
     }
+}
 
-    // let local_hist_bkgd_style = PrimitiveStyleBuilder::new().fill_color(style.local_history_background).build();
-    // let remote_hist_bkgd_style = PrimitiveStyleBuilder::new().fill_color(style.remote_history_background).build();
-    for line in rline.iter_history() {
-        // Bail once we run out of screen
-        y_idx = match y_idx.checked_sub(char_pixels_y) {
-            Some(y) => y,
-            None => return Ok(()),
+#[cfg(test)]
+mod direction_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+        prelude::Point,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_bw, BwStyle, Direction};
+
+    #[test]
+    fn top_down() {
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("a").unwrap();
+        rline.append_local_line("b").unwrap();
+        rline.append_local_char(b'x').unwrap();
+
+        let style = BwStyle {
+            selected_index: Some(0),
+            cursor_visible: false,
+            direction: Direction::TopDown,
+            ..BwStyle::new(
+                BinaryColor::Off,
+                MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            )
         };
-        let (lgutter, rgutter) = match line.status() {
-            Source::Local => (">|", "|>"),
-            Source::Remote => ("<|", "|<"),
-        } ;
+        let stats = drawer_bw(&mut disp, &rline, style).unwrap();
+        assert_eq!(stats.editing_lines_drawn, 1);
+        assert_eq!(stats.history_lines_drawn, 2);
 
-        let font_y = (y_idx + style.font.font.baseline) as i32;
+        // "x" is being edited, on the top row. Just below it is "b", the
+        // newest history line, which is selected.
+        assert_eq!(disp.get_pixel(Point::new(63, 9)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(63, 10)), Some(BinaryColor::On));
+        assert_eq!(disp.get_pixel(Point::new(63, 19)), Some(BinaryColor::On));
+        assert_eq!(disp.get_pixel(Point::new(63, 20)), Some(BinaryColor::Off));
+    }
 
-        // Left gutter
-        let lgpt = Point { x: 0, y: font_y };
-        Text::new(lgutter, lgpt, style.font).draw(disp)?;
+    #[test]
+    fn cursor_with_nothing_typed() {
+        let rline = RingLine::<4, 8>::new();
 
-        // Text
-        let ltpt = Point {
-            x: l_gutter as i32,
-            y: font_y,
+        for (direction, rows) in [(Direction::BottomUp, 54..64), (Direction::TopDown, 0..10)] {
+            let mut disp = MockDisplay::<BinaryColor>::new();
+            disp.set_allow_overdraw(true);
+            let style = BwStyle {
+                direction,
+                ..BwStyle::new(
+                    BinaryColor::Off,
+                    MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+                )
+            };
+            let x = style.gutter_chars().0 as i32 * 6;
+            drawer_bw(&mut disp, &rline, style).unwrap();
+
+            // Only one character cell is lit, at the start of the first row
+            let cell = (x..x + 6).flat_map(|x| rows.clone().map(move |y| Point::new(x, y)));
+            assert!(
+                cell.clone()
+                    .all(|pt| disp.get_pixel(pt) == Some(BinaryColor::On)),
+                "{direction:?}"
+            );
+            let lit = (0..64)
+                .flat_map(|x| (0..64).map(move |y| Point::new(x, y)))
+                .filter(|pt| disp.get_pixel(*pt) == Some(BinaryColor::On));
+            assert_eq!(lit.count(), 6 * 10, "{direction:?}");
+        }
+    }
+
+    #[test]
+    fn cursor_on_full_row() {
+        // 64px across is 10 characters, less two on each side for the gutters
+        let mut rline = RingLine::<4, 8>::new();
+        "abcdef"
+            .bytes()
+            .for_each(|c| rline.append_local_char(c).unwrap());
+
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let style = BwStyle::new(
+            BinaryColor::Off,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+        );
+        drawer_bw(&mut disp, &rline, style).unwrap();
+
+        // The cursor covers the last character rather than spilling into the
+        // gap before the right gutter
+        let cell = |xs: core::ops::Range<i32>| {
+            xs.flat_map(|x| (54..64).map(move |y| Point::new(x, y)))
+                .map(|pt| disp.get_pixel(pt))
+                .collect::<Vec<_>>()
         };
-        Text::new(line.as_str(), ltpt, style.font).draw(disp)?;
+        assert!(cell(42..48).iter().all(|px| *px == Some(BinaryColor::On)));
+        assert!(cell(48..52).iter().all(|px| *px != Some(BinaryColor::On)));
+    }
+}
 
-        // Right gutter
-        let rgpt = Point {
-            x: r_gutter as i32,
-            y: font_y,
+#[cfg(test)]
+mod side_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+        prelude::Point,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_bw, BwStyle, Side};
+
+    /// Is anything drawn in columns `xs` of the bottom row?
+    fn lit(disp: &MockDisplay<BinaryColor>, xs: core::ops::Range<i32>) -> bool {
+        xs.flat_map(|x| (54..64).map(move |y| Point::new(x, y)))
+            .any(|pt| disp.get_pixel(pt) == Some(BinaryColor::On))
+    }
+
+    #[test]
+    fn local_on_right() {
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("a").unwrap();
+
+        for (side, text_at) in [(Side::Left, 12..18), (Side::Right, 46..52)] {
+            let mut disp = MockDisplay::<BinaryColor>::new();
+            disp.set_allow_overdraw(true);
+            let style = BwStyle {
+                cursor_visible: false,
+                local_side: side,
+                local_prefix: "",
+                ..BwStyle::new(
+                    BinaryColor::Off,
+                    MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+                )
+            };
+            drawer_bw(&mut disp, &rline, style).unwrap();
+
+            // Only the one character cell between the gutters is drawn to
+            assert!(lit(&disp, text_at.clone()), "{side:?}");
+            assert!(!lit(&disp, 18..46), "{side:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod view_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_bw_view, BwStyle, ViewState};
+
+    #[test]
+    fn scrolling() {
+        let mut view = ViewState::new();
+        view.scroll_up(3);
+        assert_eq!(view.scroll_offset, 3);
+        assert!(!view.follow_tail);
+        view.scroll_down(2);
+        assert!(!view.follow_tail);
+        view.scroll_down(2);
+        assert_eq!(view, ViewState::new());
+        view.scroll_up(1);
+        view.jump_to_bottom();
+        assert_eq!(view, ViewState::new());
+    }
+
+    #[test]
+    fn follows_new_lines() {
+        let mut rline = RingLine::<8, 8>::new();
+        rline.append_local_line("a").unwrap();
+        rline.append_local_line("b").unwrap();
+        let style = || {
+            BwStyle::new(
+                BinaryColor::Off,
+                MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            )
+        };
+        let draw = |rline: &RingLine<8, 8>, view: &mut ViewState| {
+            let mut disp = MockDisplay::<BinaryColor>::new();
+            disp.set_allow_overdraw(true);
+            drawer_bw_view(&mut disp, rline, style(), view).unwrap()
         };
-        Text::new(rgutter, rgpt, style.font).draw(disp)?;
+
+        // Scrolled past the oldest line, so held at it
+        let mut view = ViewState::new();
+        view.scroll_up(5);
+        assert_eq!(draw(&rline, &mut view).history_lines_drawn, 1);
+        assert_eq!(view.scroll_offset, 1);
+
+        // Following, the offset is reset as new lines arrive
+        let mut view = ViewState {
+            scroll_offset: 1,
+            follow_tail: true,
+        };
+        rline.append_local_line("c").unwrap();
+        assert_eq!(draw(&rline, &mut view).history_lines_drawn, 3);
+        assert_eq!(view.scroll_offset, 0);
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod bubble_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+        prelude::Point,
+        primitives::PrimitiveStyle,
+    };
+
+    use super::{draw_bubble, Direction, RowCursor, WrapMode};
+
+    #[test]
+    fn partially_visible() {
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let font = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let bkgd = PrimitiveStyle::with_fill(BinaryColor::Off);
+
+        // Three rows of 10px, but only 25px of room: the bottom two rows are drawn
+        let res = draw_bubble(
+            &mut disp,
+            "aaaa bbbb cccc",
+            font,
+            &bkgd,
+            0,
+            24,
+            &mut RowCursor::new(25, Direction::BottomUp),
+            WrapMode::WordSoft,
+        );
+        assert_eq!(res, Ok(None));
+        assert_eq!(disp.get_pixel(Point::new(23, 5)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(23, 24)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(23, 4)), None);
+        assert_eq!(disp.get_pixel(Point::new(23, 25)), None);
+
+        // With enough room, the whole bubble is drawn
+        let res = draw_bubble(
+            &mut disp,
+            "aaaa bbbb cccc",
+            font,
+            &bkgd,
+            0,
+            24,
+            &mut RowCursor::new(60, Direction::BottomUp),
+            WrapMode::WordSoft,
+        );
+        assert_eq!(res, Ok(Some(30)));
+    }
+
+    #[test]
+    fn partially_visible_top_down() {
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let font = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let bkgd = PrimitiveStyle::with_fill(BinaryColor::Off);
+
+        // Three rows of 10px, but only 25px of room: the top two rows are drawn
+        let mut cursor = RowCursor::new(25, Direction::TopDown);
+        let res = draw_bubble(
+            &mut disp,
+            "aaaa bbbb cccc",
+            font,
+            &bkgd,
+            0,
+            24,
+            &mut cursor,
+            WrapMode::WordSoft,
+        );
+        assert_eq!(res, Ok(None));
+        assert_eq!(disp.get_pixel(Point::new(23, 0)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(23, 19)), Some(BinaryColor::Off));
+        assert_eq!(disp.get_pixel(Point::new(23, 20)), None);
+
+        // With enough room, bubbles stack downwards
+        let mut cursor = RowCursor::new(60, Direction::TopDown);
+        let res = draw_bubble(
+            &mut disp,
+            "aaaa",
+            font,
+            &bkgd,
+            0,
+            24,
+            &mut cursor,
+            WrapMode::WordSoft,
+        );
+        assert_eq!(res, Ok(Some(0)));
+        let res = draw_bubble(
+            &mut disp,
+            "aaaa bbbb",
+            font,
+            &bkgd,
+            0,
+            24,
+            &mut cursor,
+            WrapMode::WordSoft,
+        );
+        assert_eq!(res, Ok(Some(10)));
+        assert_eq!(cursor.room(), 30);
+    }
+}
+
+#[cfg(test)]
+mod color_style_tests {
+    use embedded_graphics::{
+        mono_font::{ascii::FONT_10X20, MonoTextStyle},
+        pixelcolor::Rgb888,
+        prelude::{RgbColor, WebColors},
+    };
+    use input_mgr::{Region, Source};
+    use profont::PROFONT_12_POINT;
+
+    use super::ColorStyleBuilder;
+
+    #[test]
+    fn builder_defaults() {
+        let style = ColorStyleBuilder::new(&PROFONT_12_POINT, Rgb888::WHITE, Rgb888::BLACK)
+            .with_history_fonts(
+                MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::BLACK),
+                MonoTextStyle::new(&PROFONT_12_POINT, Rgb888::RED),
+            )
+            .with_system_background(Rgb888::CSS_DIM_GRAY)
+            .build();
+
+        assert_eq!(style.background, Rgb888::BLACK);
+        assert_eq!(style.local_editing_font.text_color, Some(Rgb888::WHITE));
+        assert_eq!(style.system_history_font.text_color, Some(Rgb888::WHITE));
+        assert_eq!(style.local_history_font.text_color, Some(Rgb888::BLACK));
+        assert_eq!(style.remote_history_font.text_color, Some(Rgb888::RED));
+        assert_eq!(style.local_history_background, Rgb888::BLACK);
+        assert_eq!(style.system_history_background, Rgb888::CSS_DIM_GRAY);
+        assert_eq!(style.margin_chars, 0);
+    }
+
+    #[test]
+    fn geometry() {
+        let style = ColorStyleBuilder::new(&PROFONT_12_POINT, Rgb888::WHITE, Rgb888::BLACK)
+            .with_system_font(MonoTextStyle::new(&FONT_10X20, Rgb888::WHITE))
+            .build();
+
+        assert_eq!(style.max_char_width(), 10);
+        assert_eq!(style.row_height(Source::System, Region::History), 20);
+        assert_eq!(
+            style.row_height(Source::System, Region::Local),
+            PROFONT_12_POINT.character_size.height
+        );
+        assert_eq!(
+            style.row_height(Source::Remote, Region::Free),
+            PROFONT_12_POINT.character_size.height
+        );
+    }
+}
+
+#[cfg(test)]
+mod area_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::ascii::FONT_6X10,
+        pixelcolor::Rgb888,
+        prelude::{Point, RgbColor, Size},
+        primitives::Rectangle,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_color_in, ColorStyleBuilder};
+
+    #[test]
+    fn clipped_to_area() {
+        let mut disp = MockDisplay::<Rgb888>::new();
+        disp.set_allow_overdraw(true);
+        let style = ColorStyleBuilder::new(&FONT_6X10, Rgb888::WHITE, Rgb888::BLUE).build();
+        let mut rline = RingLine::<4, 16>::new();
+        rline.append_local_line("hello world").unwrap();
+
+        let area = Rectangle::new(Point::new(8, 8), Size::new(24, 32));
+        let stats = drawer_color_in(&mut disp, &rline, style, area).unwrap();
+        assert_eq!(stats.history_lines_drawn, 1);
+
+        // The area is filled, nothing outside of it is touched
+        assert_eq!(disp.get_pixel(Point::new(8, 8)), Some(Rgb888::BLUE));
+        assert!(disp.get_pixel(Point::new(31, 39)).is_some());
+        assert_eq!(disp.get_pixel(Point::new(7, 8)), None);
+        assert_eq!(disp.get_pixel(Point::new(32, 39)), None);
+        assert_eq!(disp.get_pixel(Point::new(31, 40)), None);
+        assert_eq!(disp.affected_area(), area);
+    }
+
+    #[test]
+    fn margins_wider_than_area() {
+        let mut disp = MockDisplay::<Rgb888>::new();
+        let style = ColorStyleBuilder::new(&FONT_6X10, Rgb888::WHITE, Rgb888::BLUE)
+            .with_margin_chars(2)
+            .build();
+        let mut rline = RingLine::<4, 16>::new();
+        rline.append_local_line("hello").unwrap();
+
+        // Two characters of margin on each side are wider than the area
+        let area = Rectangle::new(Point::new(0, 0), Size::new(18, 20));
+        let stats = drawer_color_in(&mut disp, &rline, style, area).unwrap();
+        assert_eq!(stats.history_lines_drawn, 0);
+        assert!(stats.truncated);
+        assert_eq!(disp.get_pixel(Point::new(9, 10)), Some(Rgb888::BLUE));
+        assert_eq!(disp.affected_area(), area);
+    }
+}
+
+#[cfg(test)]
+mod source_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+    };
+    use input_mgr::{DisplayLine, RingLine, RingSource, Source};
+
+    use super::{drawer_bw, BwStyle};
+
+    struct Msg(Source, &'static str);
+
+    /// A fixed transcript, standing in for a real ring
+    struct Canned {
+        history: Vec<Msg>,
+        local: Vec<Msg>,
+    }
+
+    impl DisplayLine for Msg {
+        fn as_str(&self) -> &str {
+            self.1
+        }
+
+        fn status(&self) -> Source {
+            self.0
+        }
+    }
+
+    impl RingSource for Canned {
+        type Line = Msg;
+        type Iter<'a> = std::slice::Iter<'a, Self::Line>;
+
+        fn iter_history(&self) -> Self::Iter<'_> {
+            self.history.iter()
+        }
+
+        fn iter_local_editing(&self) -> Self::Iter<'_> {
+            self.local.iter()
+        }
+
+        fn iter_remote_editing(&self) -> Self::Iter<'_> {
+            [].iter()
+        }
+    }
+
+    #[test]
+    fn matches_ringline() {
+        let style = BwStyle::new(
+            BinaryColor::Off,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+        );
+
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("hi").unwrap();
+        "hello"
+            .bytes()
+            .for_each(|c| rline.append_remote_char(c).unwrap());
+        rline.submit_remote_editing();
+        rline.append_local_char(b'x').unwrap();
+        let mut expected = MockDisplay::<BinaryColor>::new();
+        expected.set_allow_overdraw(true);
+        drawer_bw(&mut expected, &rline, style.clone()).unwrap();
+
+        let canned = Canned {
+            history: vec![Msg(Source::Remote, "hello"), Msg(Source::Local, "hi")],
+            local: vec![Msg(Source::Local, "x")],
+        };
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        disp.set_allow_overdraw(true);
+        let stats = drawer_bw(&mut disp, &canned, style).unwrap();
+        assert_eq!(stats.editing_lines_drawn, 1);
+        assert_eq!(stats.history_lines_drawn, 2);
+        disp.assert_eq(&expected);
+    }
+}
+
+#[cfg(test)]
+mod partial_tests {
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        mono_font::ascii::FONT_6X10,
+        pixelcolor::Rgb888,
+        prelude::{Point, RgbColor, Size},
+        primitives::Rectangle,
+    };
+    use input_mgr::{RingLine, SnapshotBuf, Source};
+
+    use super::{drawer_color, drawer_color_partial, ColorStyleBuilder, DirtySet};
+
+    fn display() -> MockDisplay<Rgb888> {
+        let mut disp = MockDisplay::new();
+        disp.set_allow_overdraw(true);
+        disp
+    }
+
+    #[test]
+    fn only_dirty_rows() {
+        let style = ColorStyleBuilder::new(&FONT_6X10, Rgb888::WHITE, Rgb888::BLUE).build();
+        let mut rline = RingLine::<4, 8>::new();
+        let mut snap = SnapshotBuf::<4, 8>::new();
+        rline.append_local_line("one").unwrap();
+        rline.append_local_line("two").unwrap();
+        rline.append_local_char(b'x').unwrap();
+
+        let mut disp = display();
+        let stats = drawer_color(&mut disp, &rline, style.clone()).unwrap();
+        assert_eq!(stats.editing_height, 10);
+        rline.snapshot(&mut snap);
+
+        // Typing within the line only touches the editing row
+        rline.append_local_char(b'y').unwrap();
+        let dirty = DirtySet::from_diff(&rline.diff(&snap), &stats);
+        let mut touched = display();
+        drawer_color_partial(&mut touched, &rline, style.clone(), &dirty).unwrap();
+        assert_eq!(
+            touched.affected_area(),
+            Rectangle::new(Point::new(0, 54), Size::new(64, 10))
+        );
+        let stats = drawer_color_partial(&mut disp, &rline, style.clone(), &dirty).unwrap();
+        let mut expected = display();
+        drawer_color(&mut expected, &rline, style.clone()).unwrap();
+        disp.assert_eq(&expected);
+        rline.snapshot(&mut snap);
+
+        // Changing the OLDEST line leaves the newer lines alone
+        rline.set_history_status(1, Source::Remote).unwrap();
+        let dirty = DirtySet::from_diff(&rline.diff(&snap), &stats);
+        let mut touched = display();
+        drawer_color_partial(&mut touched, &rline, style.clone(), &dirty).unwrap();
+        assert_eq!(
+            touched.affected_area(),
+            Rectangle::new(Point::new(0, 0), Size::new(64, 44))
+        );
+        drawer_color_partial(&mut disp, &rline, style.clone(), &dirty).unwrap();
+        let mut expected = display();
+        drawer_color(&mut expected, &rline, style).unwrap();
+        disp.assert_eq(&expected);
+    }
+
+    #[test]
+    fn moved_lines_redraw_everything() {
+        let style = ColorStyleBuilder::new(&FONT_6X10, Rgb888::WHITE, Rgb888::BLUE).build();
+        let mut rline = RingLine::<4, 8>::new();
+        let mut snap = SnapshotBuf::<4, 8>::new();
+        rline.append_local_line("one").unwrap();
+
+        let mut disp = display();
+        let stats = drawer_color(&mut disp, &rline, style.clone()).unwrap();
+        rline.snapshot(&mut snap);
+
+        // Starting to type pushes the history up a row
+        rline.append_local_char(b'x').unwrap();
+        let dirty = DirtySet::from_diff(&rline.diff(&snap), &stats);
+        let mut touched = display();
+        drawer_color_partial(&mut touched, &rline, style.clone(), &dirty).unwrap();
+        assert_eq!(
+            touched.affected_area(),
+            Rectangle::new(Point::zero(), Size::new(64, 64))
+        );
+
+        // As does a new history line
+        rline.snapshot(&mut snap);
+        rline.submit_local_editing();
+        let diff = rline.diff(&snap);
+        assert_eq!(DirtySet::from_diff(&diff, &stats), DirtySet::all());
+    }
+}
+
+#[cfg(test)]
+mod text_tests {
+    use embedded_graphics::{
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::BinaryColor,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_text, BwStyle, Direction, Side, TextGrid, WrapMode};
+
+    fn style() -> BwStyle<'static, BinaryColor> {
+        BwStyle::new(
+            BinaryColor::Off,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+        )
+    }
+
+    #[test]
+    fn gutters_and_order() {
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("ls").unwrap();
+        "ok".bytes()
+            .for_each(|c| rline.append_remote_char(c).unwrap());
+        rline.submit_remote_editing();
+        "cd".bytes()
+            .for_each(|b| rline.append_local_char(b).unwrap());
+
+        let mut grid = TextGrid::<10, 4>::new();
+        let stats = drawer_text(&mut grid, &rline, style());
+        assert_eq!(stats.history_lines_drawn, 2);
+        assert_eq!(stats.editing_lines_drawn, 1);
+        assert!(!stats.truncated);
+        assert_eq!(
+            grid.to_string(),
+            "\n\
+             >|ls    |>\n\
+             <|ok    |<\n\
+             > cd     #"
+        );
+    }
+
+    #[test]
+    fn scrolled_history() {
+        let mut rline = RingLine::<4, 8>::new();
+        rline.append_local_line("ls").unwrap();
+        rline.append_local_line("pwd").unwrap();
+        "cd".bytes()
+            .for_each(|b| rline.append_local_char(b).unwrap());
+
+        // The editing line stays on the bottom row, however far back it scrolls
+        let mut grid = TextGrid::<10, 3>::new();
+        let scrolled = BwStyle {
+            scroll_offset: 1,
+            ..style()
+        };
+        let stats = drawer_text(&mut grid, &rline, scrolled);
+        assert_eq!(stats.history_lines_drawn, 1);
+        assert_eq!(stats.editing_lines_drawn, 1);
+        assert_eq!(grid.to_string(), "\n>|ls    |>\n> cd     #");
+
+        // Past the end, there is no history left to draw
+        for scroll_offset in [2, 100, usize::MAX] {
+            let scrolled = BwStyle {
+                scroll_offset,
+                ..style()
+            };
+            let stats = drawer_text(&mut grid, &rline, scrolled);
+            assert_eq!(stats.history_lines_drawn, 0);
+            assert_eq!(stats.editing_lines_drawn, 1);
+            assert!(!stats.truncated);
+            assert_eq!(grid.to_string(), "\n\n> cd     #");
+        }
+    }
+
+    #[test]
+    fn wraps_and_truncates() {
+        let mut rline = RingLine::<4, 16>::new();
+        rline.append_local_line("oldest").unwrap();
+        rline.append_local_line("one two three").unwrap();
+
+        let mut grid = TextGrid::<10, 3>::new();
+        let style = BwStyle {
+            wrap: WrapMode::WordSoft,
+            direction: Direction::TopDown,
+            ..style()
+        };
+        let stats = drawer_text(&mut grid, &rline, style);
+        assert_eq!(stats.history_lines_drawn, 1);
+        assert!(stats.truncated);
+        assert_eq!(grid.row(0), ">|one   |>");
+        assert_eq!(grid.row(1), ">|two   |>");
+        assert_eq!(grid.row(2), ">|three |>");
+    }
+
+    #[test]
+    fn right_side() {
+        let mut rline = RingLine::<4, 8>::new();
+        "hi".bytes()
+            .for_each(|c| rline.append_remote_char(c).unwrap());
+        rline.submit_remote_editing();
+
+        let mut grid = TextGrid::<10, 1>::new();
+        let style = BwStyle {
+            remote_side: Side::Right,
+            ..style()
+        };
+        drawer_text(&mut grid, &rline, style);
+        assert_eq!(grid.row(0), "    <|hi|<");
+    }
+}
+
+#[cfg(test)]
+mod degenerate_tests {
+    use embedded_graphics::{
+        image::ImageRaw,
+        mock_display::MockDisplay,
+        mono_font::{mapping::StrGlyphMapping, DecorationDimensions, MonoFont, MonoTextStyle},
+        pixelcolor::BinaryColor,
+        prelude::Size,
+    };
+    use input_mgr::RingLine;
+
+    use super::{drawer_bw, drawer_color, BwStyle, ColorStyleBuilder};
+
+    const NO_GLYPHS: StrGlyphMapping<'static> = StrGlyphMapping::new("", 0);
+
+    fn zero_font() -> MonoFont<'static> {
+        MonoFont {
+            image: ImageRaw::new(&[], 1),
+            character_size: Size::zero(),
+            character_spacing: 0,
+            baseline: 0,
+            strikethrough: DecorationDimensions::new(0, 0),
+            underline: DecorationDimensions::new(0, 0),
+            glyph_mapping: &NO_GLYPHS,
+        }
+    }
+
+    fn rline() -> RingLine<4, 8> {
+        let mut rline = RingLine::new();
+        rline.append_local_line("hello").unwrap();
+        rline.append_local_char(b'x').unwrap();
+        rline
+    }
+
+    #[test]
+    fn bw_zero_size_font() {
+        let font = zero_font();
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        let style = BwStyle::new(BinaryColor::Off, MonoTextStyle::new(&font, BinaryColor::On));
+        let stats = drawer_bw(&mut disp, &rline(), style).unwrap();
+        assert!(stats.truncated);
+        assert_eq!(stats.editing_lines_drawn + stats.history_lines_drawn, 0);
+    }
+
+    #[test]
+    fn color_zero_size_font() {
+        let font = zero_font();
+        let mut disp = MockDisplay::<BinaryColor>::new();
+        let style = ColorStyleBuilder::new(&font, BinaryColor::On, BinaryColor::Off).build();
+        let stats = drawer_color(&mut disp, &rline(), style).unwrap();
+        assert!(stats.truncated);
+        assert_eq!(stats.editing_lines_drawn + stats.history_lines_drawn, 0);
+    }
 }